@@ -295,7 +295,24 @@ export async function lockPayroll(
       'lock_payroll',
       employerScVal,
       nativeToScVal(totalAmount, { type: 'i128' }),
-      nativeToScVal(payoutDate, { type: 'u64' })
+      nativeToScVal(payoutDate, { type: 'u64' }),
+      // min_shares: 0 accepts whatever the vault mints
+      nativeToScVal(0, { type: 'i128' }),
+      // operator: None, the employer authorizes the lock directly
+      nativeToScVal(null),
+      // options: defaults (default yield split, no idempotency key, invest immediately, no label, sequential batch id)
+      nativeToScVal(
+        { employer_yield_bps: null, idempotency_key: null, invest: null, label: null, salt: null },
+        {
+          type: {
+            employer_yield_bps: ['symbol', null],
+            idempotency_key: ['symbol', null],
+            invest: ['symbol', null],
+            label: ['symbol', null],
+            salt: ['symbol', null],
+          },
+        }
+      )
     );
 
     const txHash = await buildAndSubmitTransaction(operation);
@@ -391,6 +408,12 @@ export async function releaseToSDP(
 
     logger.debug('Building contract call for release_to_sdp');
 
+    if (!adminKeypair) {
+      throw new ContractError('Admin keypair not configured', {
+        configMissing: 'ADMIN_SECRET_KEY',
+      });
+    }
+
     // Build contract call
     const employerScVal = employerAddress.startsWith('G')
       ? nativeToScVal(Keypair.fromPublicKey(employerAddress).publicKey(), { type: 'address' })
@@ -399,11 +422,21 @@ export async function releaseToSDP(
       ? nativeToScVal(Keypair.fromPublicKey(sdpWalletAddress).publicKey(), { type: 'address' })
       : new Address(sdpWalletAddress).toScVal();
     
+    // The backend's admin keypair must be on the contract's operator allowlist
+    const operatorScVal = nativeToScVal(adminKeypair.publicKey(), { type: 'address' });
+
+    // destination: ReleaseDestination::Sdp(wallet), encoded as [variant, payload]
+    const destinationScVal = StellarSdk.xdr.ScVal.scvVec([
+      StellarSdk.xdr.ScVal.scvSymbol('Sdp'),
+      sdpWalletScVal,
+    ]);
+
     const operation = contract.call(
       'release_to_sdp',
+      operatorScVal,
       employerScVal,
       nativeToScVal(BigInt(batchId), { type: 'u64' }),
-      sdpWalletScVal
+      destinationScVal
     );
 
     const txHash = await buildAndSubmitTransaction(operation);
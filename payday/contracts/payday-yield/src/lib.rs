@@ -8,17 +8,38 @@
 // - Allowing the employer to collect the yield after the payout
 
 #![no_std]
+// `Events::publish` is deprecated in soroban-sdk 23 in favour of `#[contractevent]`,
+// but the backend indexes the existing tuple topics, so keep publishing them as-is.
+#![allow(deprecated)]
 use soroban_sdk::{
     auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
     contract, contracterror, contractimpl, contracttype, symbol_short, 
-    token::TokenClient, vec, Address, Env, IntoVal, Symbol, Vec
+    token::TokenClient, vec, xdr::{FromXdr, ToXdr}, Address, Bytes, BytesN, Env, IntoVal, Map, String, Symbol, TryFromVal, Val, Vec
 };
 
 mod defindex_client {
-    use soroban_sdk::{Address, Env, Vec, contractclient};
+    use soroban_sdk::{Address, Env, Map, String, Vec, contractclient, contracttype};
+    
+    /// A strategy the vault allocates an asset to
+    #[contracttype]
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub struct Strategy {
+        pub address: Address,
+        pub name: String,
+        pub paused: bool,
+    }
+    
+    /// A vault asset and the strategies it is invested through
+    #[contracttype]
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub struct AssetStrategySet {
+        pub address: Address,
+        pub strategies: Vec<Strategy>,
+    }
     
     /// DeFindex Vault Client Interface
     /// Based on: https://github.com/paltalabs/defindex/blob/main/apps/contracts/vault/src/interface.rs
+    #[allow(dead_code)]
     #[contractclient(name = "DefindexVaultClient")]
     pub trait DefindexVault {
         /// Deposit assets into the vault and receive vault shares
@@ -39,14 +60,143 @@ mod defindex_client {
             min_amounts_out: Vec<i128>,
             from: Address,
         ) -> Vec<i128>;
+        
+        /// Start redeeming shares from strategies with a redemption delay
+        /// Returns: Timestamp from which `withdraw` of those shares succeeds
+        fn request_withdraw(e: Env, df_amount: i128, from: Address) -> u64;
+        
+        /// Total vault shares in circulation (the vault is itself a token)
+        fn total_supply(e: Env) -> i128;
+        
+        /// Underlying asset amounts currently backing the given number of shares
+        fn get_asset_amounts_per_shares(e: Env, vault_shares: i128) -> Vec<i128>;
+        
+        /// Assets managed by the vault, in the order deposits and withdrawals use
+        fn get_assets(e: Env) -> Vec<AssetStrategySet>;
+        
+        /// Idle (uninvested) balance per asset, available for immediate withdrawal
+        fn fetch_current_idle_funds(e: Env) -> Map<Address, i128>;
     }
 }
 
 use defindex_client::DefindexVaultClient;
 
+// Contract ABI version, bumped on every breaking interface or storage change
+const CONTRACT_VERSION: u32 = 22;
+
 // Storage TTL constants
 const INSTANCE_BUMP_AMOUNT: u32 = 7776000; // 90 days
 const INSTANCE_LIFETIME_THRESHOLD: u32 = 518400; // 6 days
+const RECORD_BUMP_AMOUNT: u32 = 7776000; // 90 days, per lock or employer record in persistent storage
+const RECORD_LIFETIME_THRESHOLD: u32 = 518400; // 6 days
+
+// Fee constants
+const BPS_DENOMINATOR: i128 = 10000;
+const MAX_PLATFORM_FEE_BPS: u32 = 1000; // 10% cap on the platform's cut of yield
+
+// Time past a batch's payout date before admin_cancel_lock may recover it
+const ADMIN_CANCEL_DELAY_SECS: u64 = 90 * 24 * 60 * 60; // 90 days
+
+// Yield estimate used by calculate_current_yield when no APY is configured
+const DEFAULT_ESTIMATE_APY_BPS: u32 = 400; // 4%
+
+// Fixed-point scale for get_share_price (7 decimals, like Stellar assets)
+const SHARE_PRICE_SCALE: i128 = 10_000_000;
+
+// Transitions kept per batch by get_lock_history (oldest dropped beyond this)
+const MAX_HISTORY: u32 = 16;
+
+// Upper bound on the locks one lock_payroll_recurring call creates (a year of monthly payrolls)
+const MAX_RECURRING_LOCKS: u32 = 12;
+
+// Upper bound on batch ids accepted by get_status_batch
+const MAX_STATUS_BATCH: u32 = 50;
+
+// High bit marking salt-derived batch ids, keeping them apart from the sequential ones
+const SALTED_BATCH_FLAG: u64 = 1 << 63;
+
+// How long a pending privileged-operation approval waits for co-signers (~1 day of ledgers)
+const APPROVAL_TTL_LEDGERS: u32 = 17280;
+
+// Longest batch label accepted by lock_payroll, in bytes
+const MAX_LABEL_LEN: u32 = 32;
+
+// Upper bound on the employers or batches one paginated call walks
+const MAX_PAGE: u32 = 50;
+
+// Helper function to extend the instance TTL
+fn bump_instance(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+// Helper function to read a per-lock or per-employer record from persistent storage,
+// extending its TTL
+fn load<V: TryFromVal<Env, Val>>(env: &Env, key: &DataKey) -> Option<V> {
+    let value = env.storage().persistent().get(key);
+    if value.is_some() {
+        env.storage()
+            .persistent()
+            .extend_ttl(key, RECORD_LIFETIME_THRESHOLD, RECORD_BUMP_AMOUNT);
+    }
+    value
+}
+
+// Helper function to write a per-lock or per-employer record to persistent storage
+fn store<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+    env.storage().persistent().set(key, value);
+    env.storage()
+        .persistent()
+        .extend_ttl(key, RECORD_LIFETIME_THRESHOLD, RECORD_BUMP_AMOUNT);
+}
+
+// Helper function to check for a per-lock or per-employer record
+fn has_record(env: &Env, key: &DataKey) -> bool {
+    env.storage().persistent().has(key)
+}
+
+// Helper function to delete a per-lock or per-employer record
+fn drop_record(env: &Env, key: &DataKey) {
+    env.storage().persistent().remove(key);
+}
+
+// Helper function to count the vault shares a batch still holds: its principal shares
+// until release, then the shares left invested for its yield
+fn held_shares(lock: &PayrollLock) -> i128 {
+    if lock.status == LockStatus::Locked { lock.vault_shares } else { lock.yield_shares }
+}
+
+// Helper function to add `delta` to the shares held for batches in `vault`
+fn adjust_held_shares(env: &Env, vault: &Address, delta: i128) {
+    if delta == 0 {
+        return;
+    }
+    for key in [DataKey::HeldShares(vault.clone()), DataKey::TotalHeldShares] {
+        let held: i128 = env.storage().instance().get(&key).unwrap_or(0);
+        env.storage().instance().set(&key, &held.saturating_add(delta));
+    }
+}
+
+// Helper function to save a batch under `key`, keeping the per-vault held share totals
+// in step so nothing has to walk every batch to find them
+fn save_lock(env: &Env, key: &DataKey, lock: &PayrollLock) {
+    let previous: Option<PayrollLock> = load(env, key);
+    if let Some(previous) = previous {
+        adjust_held_shares(env, &previous.vault, -held_shares(&previous));
+    }
+    adjust_held_shares(env, &lock.vault, held_shares(lock));
+    store(env, key, lock);
+}
+
+// Helper function to delete the batch under `key`, releasing its held shares
+fn remove_lock(env: &Env, key: &DataKey) {
+    let previous: Option<PayrollLock> = load(env, key);
+    if let Some(previous) = previous {
+        adjust_held_shares(env, &previous.vault, -held_shares(&previous));
+    }
+    drop_record(env, key);
+}
 
 // Helper function to validate amounts
 fn check_nonnegative_amount(amount: i128) -> Result<(), Error> {
@@ -56,26 +206,799 @@ fn check_nonnegative_amount(amount: i128) -> Result<(), Error> {
     Ok(())
 }
 
+// Helper function to require the stored admin's authorization
+fn require_admin(env: &Env) -> Result<Address, Error> {
+    let admin: Address = env.storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .ok_or(Error::NotInitialized)?;
+    admin.require_auth();
+    Ok(admin)
+}
+
+// Helper function to reject state changes during an emergency freeze
+fn require_not_frozen(env: &Env) -> Result<(), Error> {
+    if env.storage().instance().has(&DataKey::Frozen) {
+        return Err(Error::ContractFrozen);
+    }
+    Ok(())
+}
+
+// Helper function to combine the admin and co-admins into a deduplicated list
+fn distinct_admins(env: &Env, admin: Address, co_admins: Option<Vec<Address>>) -> Vec<Address> {
+    let mut admins = vec![env, admin];
+    for co_admin in co_admins.unwrap_or(Vec::new(env)).iter() {
+        if !admins.contains(&co_admin) {
+            admins.push_back(co_admin);
+        }
+    }
+    admins
+}
+
+// Helper function to get the admins that approve privileged operations and the threshold
+fn admin_set(env: &Env) -> Result<(Vec<Address>, u32), Error> {
+    let admin: Address = env.storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .ok_or(Error::NotInitialized)?;
+    let settings = settings(env);
+    
+    Ok((distinct_admins(env, admin, settings.co_admins), settings.admin_threshold.unwrap_or(1)))
+}
+
+// Helper function to require the authorization of any admin or co-admin, for admin views
+// that need no threshold of approvals
+fn require_any_admin(env: &Env, caller: &Address) -> Result<(), Error> {
+    caller.require_auth();
+    
+    let (admins, _) = admin_set(env)?;
+    if !admins.contains(caller) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+// Helper function to record an admin's approval of a privileged operation, identified by
+// the XDR of its name and arguments. Returns true once `admin_threshold` distinct admins
+// have approved, consuming the approvals; until then they wait in temporary storage
+fn approve_privileged(env: &Env, approver: &Address, operation: Bytes) -> Result<bool, Error> {
+    approver.require_auth();
+    
+    let (admins, threshold) = admin_set(env)?;
+    if !admins.contains(approver) {
+        return Err(Error::Unauthorized);
+    }
+    
+    let key = DataKey::Approvals(env.crypto().sha256(&operation).to_bytes());
+    let mut approvals: Vec<Address> = env.storage()
+        .temporary()
+        .get(&key)
+        .unwrap_or(Vec::new(env));
+    if !approvals.contains(approver) {
+        approvals.push_back(approver.clone());
+    }
+    
+    if approvals.len() >= threshold {
+        env.storage().temporary().remove(&key);
+        return Ok(true);
+    }
+    
+    env.storage().temporary().set(&key, &approvals);
+    env.storage().temporary().extend_ttl(&key, APPROVAL_TTL_LEDGERS, APPROVAL_TTL_LEDGERS);
+    env.events().publish((symbol_short!("approved"), approver.clone()), (operation, approvals.len()));
+    Ok(false)
+}
+
+// Helper function to require an allowlisted operator's authorization
+fn require_operator(env: &Env, operator: &Address) -> Result<(), Error> {
+    operator.require_auth();
+    
+    let operators: Vec<Address> = env.storage()
+        .instance()
+        .get(&DataKey::Operators)
+        .unwrap_or(Vec::new(env));
+    if !operators.contains(operator) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+// Helper function to compute the platform's cut of a batch's yield
+fn platform_fee(env: &Env, lock: &PayrollLock, yield_earned: i128) -> Result<i128, Error> {
+    // A batch's negotiated fee overrides the contract-wide fee
+    let fee_bps: u32 = match lock.platform_fee_bps {
+        Some(platform_fee_bps) => platform_fee_bps,
+        None => env.storage()
+            .instance()
+            .get(&DataKey::PlatformFeeBps)
+            .unwrap_or(0),
+    };
+    
+    yield_earned
+        .checked_mul(fee_bps as i128)
+        .and_then(|v| v.checked_div(BPS_DENOMINATOR))
+        .ok_or(Error::InvalidAmount)
+}
+
+// Helper function to send the platform's cut of `yield_amount`, held by the contract in the
+// batch's token, to the fee collector; returns the fee taken
+fn collect_platform_fee(env: &Env, lock: &PayrollLock, batch_id: u64, yield_amount: i128) -> Result<i128, Error> {
+    if yield_amount <= 0 {
+        return Ok(0);
+    }
+    let fee = platform_fee(env, lock, yield_amount)?;
+    if fee > 0 {
+        let fee_collector: Address = env.storage()
+            .instance()
+            .get(&DataKey::FeeCollector)
+            .ok_or(Error::NotInitialized)?;
+        TokenClient::new(env, &lock_token(lock)).transfer(&env.current_contract_address(), &fee_collector, &fee);
+        env.events().publish((symbol_short!("fee"), batch_id), (fee_collector, fee));
+    }
+    Ok(fee)
+}
+
+// Helper function to get the token a batch's principal and yield are accounted in: its first
+// asset, which may differ from the configured token if the instance's token ever changes
+fn lock_token(lock: &PayrollLock) -> Address {
+    lock.assets.get_unchecked(0).0
+}
+
+// Helper function to express the platform fee and the employees' share (taken after the fee)
+// as parts of the whole yield; the employer gets the rest, so the parts sum to 10000
+fn split_config(platform_fee_bps: u32, employee_yield_bps: u32) -> Result<SplitConfig, Error> {
+    let platform = platform_fee_bps as i128;
+    if platform > BPS_DENOMINATOR || employee_yield_bps as i128 > BPS_DENOMINATOR {
+        return Err(Error::InvalidConfig);
+    }
+    
+    let employee = (BPS_DENOMINATOR - platform) * employee_yield_bps as i128 / BPS_DENOMINATOR;
+    let employer = BPS_DENOMINATOR - platform - employee;
+    Ok(SplitConfig {
+        employer_bps: employer as u32,
+        employee_bps: employee as u32,
+        platform_fee_bps,
+    })
+}
+
+// Helper function to compute the employees' cut of a batch's yield left after the platform fee
+fn employee_share(env: &Env, lock: &PayrollLock, net_yield: i128) -> Result<i128, Error> {
+    // A batch's negotiated split overrides the contract-wide one
+    let employee_bps = match lock.employer_yield_bps {
+        Some(employer_yield_bps) => BPS_DENOMINATOR as u32 - employer_yield_bps,
+        None => settings(env).employee_yield_bps.unwrap_or(0),
+    };
+    net_yield
+        .checked_mul(employee_bps as i128)
+        .and_then(|v| v.checked_div(BPS_DENOMINATOR))
+        .ok_or(Error::InvalidAmount)
+}
+
+// Helper function to split a vault withdrawal into (yield_earned, principal_loss)
+// Shortfalls within the configured rounding tolerance are treated as zero yield
+// rather than a loss, since share -> asset conversion can round down by dust.
+fn split_withdrawal(env: &Env, total_amount: i128, total_withdrawn: i128) -> (i128, i128) {
+    let tolerance = settings(env).rounding_tolerance.unwrap_or(0);
+    
+    if total_withdrawn >= total_amount {
+        (total_withdrawn - total_amount, 0)
+    } else if total_amount - total_withdrawn <= tolerance {
+        (0, 0)
+    } else {
+        (0, total_amount - total_withdrawn)
+    }
+}
+
+// Helper function to count the shares worth a batch's principal at the current share value,
+// rounded up; all of them if the batch has no yield to leave invested
+fn principal_shares(env: &Env, lock: &PayrollLock) -> Result<i128, Error> {
+    let value = DefindexVaultClient::new(env, &lock.vault)
+        .get_asset_amounts_per_shares(&lock.vault_shares)
+        .get(0)
+        .unwrap_or(0);
+    if value <= lock.total_amount {
+        return Ok(lock.vault_shares);
+    }
+    
+    let shares = lock.vault_shares
+        .checked_mul(lock.total_amount)
+        .and_then(|v| v.checked_add(value - 1))
+        .and_then(|v| v.checked_div(value))
+        .ok_or(Error::InvalidAmount)?;
+    Ok(shares.min(lock.vault_shares))
+}
+
+// Helper function to count the shares a release withdraws. Single-asset batches only
+// withdraw the shares covering principal; the rest stays invested until the yield is
+// claimed, so unclaimed yield keeps compounding (redemption requests cover every share,
+// so those batches withdraw them all)
+fn release_shares(env: &Env, lock: &PayrollLock) -> Result<i128, Error> {
+    if lock.assets.len() == 1 && lock.withdrawal_requested_at == 0 {
+        principal_shares(env, lock)
+    } else {
+        Ok(lock.vault_shares)
+    }
+}
+
+// Helper function to value a batch's unclaimed yield: tokens already withdrawn plus the
+// current value of its yield shares still in the vault
+fn unclaimed_yield(env: &Env, lock: &PayrollLock) -> i128 {
+    if lock.yield_shares <= 0 {
+        return lock.yield_earned;
+    }
+    
+    let value = DefindexVaultClient::new(env, &lock.vault)
+        .get_asset_amounts_per_shares(&lock.yield_shares)
+        .get(0)
+        .unwrap_or(0);
+    lock.yield_earned.saturating_add(value)
+}
+
+// Helper function to withdraw a released batch's yield shares ahead of paying yield out,
+// adding what comes back to `yield_earned`. Saves the lock under `key`
+fn redeem_yield_shares(env: &Env, key: &DataKey, lock: &mut PayrollLock) {
+    if lock.yield_shares <= 0 {
+        return;
+    }
+    
+    // Zero the shares before calling out, like withdraw_lock
+    let shares = lock.yield_shares;
+    lock.yield_shares = 0;
+    save_lock(env, key, lock);
+    
+    let withdrawn = DefindexVaultClient::new(env, &lock.vault)
+        .withdraw(&shares, &vec![env, 0], &env.current_contract_address())
+        .get(0)
+        .unwrap_or(0);
+    lock.yield_earned = lock.yield_earned.saturating_add(withdrawn);
+    lock.realized_yield = lock.realized_yield.saturating_add(withdrawn);
+    save_lock(env, key, lock);
+}
+
+// Helper function to estimate a lock's yield accrued up to `until`
+fn estimate_yield(env: &Env, lock: &PayrollLock, until: u64) -> Result<i128, Error> {
+    let settings = settings(env);
+    let apy_bps = settings.estimate_apy_bps.unwrap_or(DEFAULT_ESTIMATE_APY_BPS);
+    
+    // Calculate yield based on time elapsed at the configured APY with overflow protection
+    // Accrual starts after the strategy warm-up; a start ahead of the ledger clock
+    // (warm-up window, skew/replay) saturates to 0 days locked
+    let accrual_start = lock.lock_date.saturating_add(settings.yield_start_delay_secs.unwrap_or(0));
+    let days_locked = until.saturating_sub(accrual_start) / 86400;
+    let rate = (apy_bps as i128)
+        .checked_mul(days_locked as i128)
+        .ok_or(Error::ArithmeticOverflow)?;
+    
+    // Split the principal around the divisor so the intermediate product only overflows
+    // when the estimate itself does: a * rate / d == (a / d) * rate + (a % d) * rate / d
+    let divisor = 365 * BPS_DENOMINATOR;
+    (lock.total_amount / divisor)
+        .checked_mul(rate)
+        .and_then(|whole| {
+            let part = (lock.total_amount % divisor).checked_mul(rate)? / divisor;
+            whole.checked_add(part)
+        })
+        .ok_or(Error::ArithmeticOverflow)
+}
+
+// Helper function to query the vault's (total_assets, total_shares)
+fn vault_totals(env: &Env) -> Result<(i128, i128), Error> {
+    let defindex_vault: Address = env.storage()
+        .instance()
+        .get(&DataKey::DefindexPoolAddress)
+        .ok_or(Error::NotInitialized)?;
+    
+    let defindex_client = DefindexVaultClient::new(env, &defindex_vault);
+    let total_shares = defindex_client.total_supply();
+    if total_shares == 0 {
+        return Ok((0, 0));
+    }
+    
+    let total_assets = defindex_client
+        .get_asset_amounts_per_shares(&total_shares)
+        .get(0)
+        .unwrap_or(0);
+    Ok((total_assets, total_shares))
+}
+
+// Helper function to look up a vault's first (for single-asset vaults, only) asset
+fn vault_token(env: &Env, vault: &Address) -> Result<Address, Error> {
+    DefindexVaultClient::new(env, vault)
+        .get_assets()
+        .get(0)
+        .map(|asset| asset.address)
+        .ok_or(Error::InvalidAssets)
+}
+
+// Helper function to check a token answers the standard token interface (any SAC,
+// including native XLM, does) before we start moving it around
+fn validate_token(env: &Env, token: &Address) -> Result<(), Error> {
+    let token_client = TokenClient::new(env, token);
+    match (token_client.try_decimals(), token_client.try_name()) {
+        (Ok(Ok(_)), Ok(Ok(_))) => Ok(()),
+        _ => Err(Error::InvalidToken),
+    }
+}
+
+// Helper function to turn a display scale into its 10^scale divisor
+fn scale_divisor(scale: u32, decimals: u32) -> Result<i128, Error> {
+    if scale > decimals {
+        return Err(Error::InvalidScale);
+    }
+    10i128.checked_pow(scale).ok_or(Error::InvalidScale)
+}
+
+// Helper function to decrease the TVL accumulator, clamped at 0 so an
+// accounting bug can't underflow-panic a release
+fn decrease_tvl(env: &Env, amount: i128) {
+    let tvl: i128 = env.storage()
+        .instance()
+        .get(&DataKey::TotalValueLocked)
+        .unwrap_or(0);
+    let new_tvl = tvl.checked_sub(amount).unwrap_or(0).max(0);
+    env.storage().instance().set(&DataKey::TotalValueLocked, &new_tvl);
+}
+
+// Helper function to record an employer in the registry the first time they get a batch
+fn register_employer(env: &Env, employer: &Address) {
+    if has_record(env, &DataKey::Registered(employer.clone())) {
+        return;
+    }
+    let count = employer_count(env);
+    store(env, &DataKey::Employer(count), employer);
+    store(env, &DataKey::Registered(employer.clone()), &true);
+    env.storage().instance().set(&DataKey::EmployerCount, &(count + 1));
+}
+
+// Helper function to count the employers that have ever had a batch
+fn employer_count(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::EmployerCount)
+        .unwrap_or(0)
+}
+
+// Helper function to list up to `limit` registered employers from position `start`
+fn employer_page(env: &Env, start: u32, limit: u32) -> Result<Vec<Address>, Error> {
+    if limit > MAX_PAGE {
+        return Err(Error::TooManyBatches);
+    }
+    
+    let end = employer_count(env).min(start.saturating_add(limit));
+    let mut employers = Vec::new(env);
+    for i in start..end {
+        let employer: Option<Address> = load(env, &DataKey::Employer(i));
+        if let Some(employer) = employer {
+            employers.push_back(employer);
+        }
+    }
+    Ok(employers)
+}
+
+// Helper function to list every batch id an employer has used, sequential then salted
+fn batch_ids(env: &Env, employer: &Address) -> Vec<u64> {
+    let next_batch_id: u64 = load(env, &DataKey::NextBatchId(employer.clone()))
+        .unwrap_or(0);
+    
+    let mut ids = Vec::new(env);
+    for batch_id in 0..next_batch_id {
+        ids.push_back(batch_id);
+    }
+    let salted: Vec<u64> = load(env, &DataKey::SaltedBatches(employer.clone()))
+        .unwrap_or(Vec::new(env));
+    ids.append(&salted);
+    ids
+}
+
+// Helper function to list up to `limit` of an employer's batch ids from position `start`,
+// in `batch_ids` order, without building the whole list
+fn batch_id_page(env: &Env, employer: &Address, start: u32, limit: u32) -> Result<Vec<u64>, Error> {
+    if limit > MAX_PAGE {
+        return Err(Error::TooManyBatches);
+    }
+    
+    let next_batch_id: u64 = load(env, &DataKey::NextBatchId(employer.clone()))
+        .unwrap_or(0);
+    let start = start as u64;
+    let end = start + limit as u64;
+    
+    let mut ids = Vec::new(env);
+    for batch_id in start.min(next_batch_id)..end.min(next_batch_id) {
+        ids.push_back(batch_id);
+    }
+    if end > next_batch_id {
+        let salted: Vec<u64> = load(env, &DataKey::SaltedBatches(employer.clone()))
+            .unwrap_or(Vec::new(env));
+        let from = start.saturating_sub(next_batch_id).min(salted.len() as u64) as u32;
+        let to = (end - next_batch_id).min(salted.len() as u64) as u32;
+        for i in from..to {
+            ids.push_back(salted.get_unchecked(i));
+        }
+    }
+    Ok(ids)
+}
+
+// Helper function to derive a batch id from sha256(employer || salt)
+fn salted_batch_id(env: &Env, employer: &Address, salt: &BytesN<32>) -> u64 {
+    let mut preimage = employer.clone().to_xdr(env);
+    preimage.append(&Bytes::from(salt.clone()));
+    let hash = env.crypto().sha256(&preimage).to_array();
+    
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&hash[..8]);
+    u64::from_be_bytes(id) | SALTED_BATCH_FLAG
+}
+
+// Helper function to sum an employer's principal in unreleased batches
+fn employer_locked(env: &Env, employer: &Address) -> i128 {
+    let mut locked: i128 = 0;
+    for batch_id in batch_ids(env, employer).iter() {
+        let lock: Option<PayrollLock> = load(env, &DataKey::PayrollLock(employer.clone(), batch_id));
+        if let Some(lock) = lock {
+            if lock.status == LockStatus::Locked {
+                locked = locked.saturating_add(lock.total_amount);
+            }
+        }
+    }
+    locked
+}
+
+// Helper function to get the shares still held for batches (unreleased principal and yield
+// not yet withdrawn), in `vault` or in any vault
+fn outstanding_shares(env: &Env, vault: Option<&Address>) -> i128 {
+    let key = match vault {
+        Some(vault) => DataKey::HeldShares(vault.clone()),
+        None => DataKey::TotalHeldShares,
+    };
+    env.storage().instance().get(&key).unwrap_or(0)
+}
+
+// Helper function to credit (or, with a negative delta, debit) an employer's deposit
+// balance, keeping the contract-wide total in step; fails if the credit would go negative
+fn adjust_deposit(env: &Env, employer: &Address, delta: i128) -> Result<i128, Error> {
+    let credit: i128 = load(env, &DataKey::Deposit(employer.clone()))
+        .unwrap_or(0);
+    let new_credit = credit.checked_add(delta).ok_or(Error::ArithmeticOverflow)?;
+    if new_credit < 0 {
+        return Err(Error::InsufficientFunds);
+    }
+    let total: i128 = env.storage()
+        .instance()
+        .get(&DataKey::TotalDeposits)
+        .unwrap_or(0);
+    let new_total = total.checked_add(delta).ok_or(Error::ArithmeticOverflow)?;
+    
+    if new_credit == 0 {
+        drop_record(env, &DataKey::Deposit(employer.clone()));
+    } else {
+        store(env, &DataKey::Deposit(employer.clone()), &new_credit);
+    }
+    env.storage().instance().set(&DataKey::TotalDeposits, &new_total.max(0));
+    Ok(new_credit)
+}
+
+// Helper function to move a batch to a new lifecycle stage, keeping the compatibility
+// flags in sync; anything but Locked -> Released -> Claimed or Locked -> Cancelled is rejected
+fn set_status(lock: &mut PayrollLock, status: LockStatus) -> Result<(), Error> {
+    let allowed = matches!(
+        (lock.status, status),
+        (LockStatus::Locked, LockStatus::Released)
+            | (LockStatus::Released, LockStatus::Claimed)
+            | (LockStatus::Locked, LockStatus::Cancelled)
+    );
+    if !allowed {
+        return Err(Error::InvalidStateTransition);
+    }
+    
+    lock.status = status;
+    lock.funds_released = matches!(status, LockStatus::Released | LockStatus::Claimed);
+    lock.yield_claimed = status == LockStatus::Claimed;
+    Ok(())
+}
+
+// Helper function to reject actions on a batch that was cancelled and refunded
+fn check_not_cancelled(lock: &PayrollLock) -> Result<(), Error> {
+    if lock.status == LockStatus::Cancelled {
+        return Err(Error::LockCancelled);
+    }
+    Ok(())
+}
+
+// Helper function to append a lifecycle transition to a batch's history
+fn record_transition(env: &Env, employer: &Address, batch_id: u64, kind: Symbol) {
+    let key = DataKey::History(employer.clone(), batch_id);
+    let mut history: Vec<Transition> = load(env, &key)
+        .unwrap_or(Vec::new(env));
+    if history.len() >= MAX_HISTORY {
+        history.pop_front();
+    }
+    history.push_back(Transition { kind, timestamp: env.ledger().timestamp() });
+    store(env, &key, &history);
+}
+
+// Helper function to fold a lifecycle step into an employer's running summary
+fn update_summary(env: &Env, employer: &Address, update: impl FnOnce(&mut EmployerSummary)) {
+    let key = DataKey::Summary(employer.clone());
+    let mut summary: EmployerSummary = load(env, &key)
+        .unwrap_or_default();
+    update(&mut summary);
+    store(env, &key, &summary);
+}
+
+// Helper function to check whether the contract holds any lock or deposit; every first lock
+// registers its employer, so an empty registry means no lock ever existed
+fn has_any_funds(env: &Env) -> bool {
+    let deposits: i128 = env.storage()
+        .instance()
+        .get(&DataKey::TotalDeposits)
+        .unwrap_or(0);
+    employer_count(env) > 0
+        || deposits > 0
+        || env.storage().instance().has(&LegacyDataKey::PayrollLock)
+}
+
+// Helper function to validate and store the initialize parameters, returning the payroll token
+fn configure(
+    env: &Env,
+    defindex_pool: &Address,
+    admin: &Address,
+    platform_fee_bps: u32,
+    fee_collector: &Address,
+    settings: &Settings,
+) -> Result<Address, Error> {
+    // The vault and token can't change under funds already held in them
+    if has_any_funds(env) {
+        return Err(Error::AlreadyInUse);
+    }
+    
+    if platform_fee_bps > MAX_PLATFORM_FEE_BPS {
+        return Err(Error::InvalidFee);
+    }
+    
+    check_nonnegative_amount(settings.rounding_tolerance.unwrap_or(0))?;
+    check_nonnegative_amount(settings.max_locked_per_employer.unwrap_or(0))?;
+    check_nonnegative_amount(settings.min_claimable_yield.unwrap_or(0))?;
+    if settings.keeper_reward_bps.unwrap_or(0) as i128 > BPS_DENOMINATOR {
+        return Err(Error::InvalidFee);
+    }
+    split_config(platform_fee_bps, settings.employee_yield_bps.unwrap_or(0))?;
+    
+    // The threshold must be reachable by the distinct admins
+    let admins = distinct_admins(env, admin.clone(), settings.co_admins.clone());
+    let threshold = settings.admin_threshold.unwrap_or(1);
+    if threshold == 0 || threshold > admins.len() {
+        return Err(Error::InvalidThreshold);
+    }
+    
+    // The vault, its token and this contract must all be different contracts
+    let this = env.current_contract_address();
+    if *defindex_pool == this {
+        return Err(Error::InvalidConfig);
+    }
+    let token = vault_token(env, defindex_pool)?;
+    if token == *defindex_pool || token == this {
+        return Err(Error::InvalidConfig);
+    }
+    validate_token(env, &token)?;
+    
+    bump_instance(env);
+    
+    env.storage().instance().set(&DataKey::DefindexPoolAddress, defindex_pool);
+    env.storage().instance().set(&DataKey::TokenAddress, &token);
+    env.storage().instance().set(&DataKey::Admin, admin);
+    env.storage().instance().set(&DataKey::PlatformFeeBps, &platform_fee_bps);
+    env.storage().instance().set(&DataKey::FeeCollector, fee_collector);
+    env.storage().instance().set(&DataKey::Settings, settings);
+    Ok(token)
+}
+
+// Helper function to read the optional settings passed at initialize
+fn settings(env: &Env) -> Settings {
+    env.storage()
+        .instance()
+        .get(&DataKey::Settings)
+        .unwrap_or_default()
+}
+
+// Optional settings passed at initialize; `None` falls back to the default noted per field
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Settings {
+    pub rounding_tolerance: Option<i128>, // Max release shortfall treated as rounding dust (None = 0)
+    pub claim_window_secs: Option<u64>, // Time after release before unclaimed yield can be swept (None = never)
+    pub min_lock_secs: Option<u64>,     // Minimum time between lock and payout (None = 0)
+    pub estimate_apy_bps: Option<u32>,  // APY used for yield estimates, e.g. 400 = 4% (None = 400)
+    pub max_locked_per_employer: Option<i128>, // Cap on one employer's unreleased principal (None = unlimited)
+    pub keeper_reward_bps: Option<u32>, // Share of yield paid to keepers that trigger a release (None = 0)
+    pub max_lock_horizon_secs: Option<u64>, // Furthest payout date allowed from now (None = unlimited)
+    pub min_claimable_yield: Option<i128>, // Smallest employer share a claim may pay out; sweeps and relocks are exempt (None = 0)
+    pub cancel_window_secs: Option<u64>, // Time after locking during which cancel_lock is allowed (None = until release)
+    pub yield_start_delay_secs: Option<u64>, // Strategy warm-up after locking before estimates accrue (None = 0)
+    pub co_admins: Option<Vec<Address>>, // Extra admins that can approve privileged operations (None = admin only)
+    pub admin_threshold: Option<u32>,    // Distinct admin approvals a privileged operation needs (None = 1)
+    pub employee_yield_bps: Option<u32>, // Employees' share of yield after platform fee, set aside on claim (None = 0)
+}
+
+// Optional per-lock knobs for lock_payroll; `None` falls back to the default noted per field
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LockOptions {
+    pub idempotency_key: Option<BytesN<32>>, // Retries with the same key return the original batch (None = no dedup)
+    pub invest: Option<bool>,                // Invest into vault strategies on deposit, or leave idle (None = true)
+    pub label: Option<String>,               // Human-readable batch name, up to 32 bytes (None = unlabeled)
+    pub salt: Option<BytesN<32>>,            // Derive an unguessable batch id from (employer, salt) (None = next sequential id)
+    pub employer_yield_bps: Option<u32>,     // Employer's share of this batch's yield after platform fee, the rest going to employees (None = Settings::employee_yield_bps split)
+}
+
 // Storage for payroll batch
 #[contracttype]
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct PayrollLock {
     pub employer: Address,
     pub total_amount: i128,          // Total locked for payroll
     pub vault_shares: i128,          // DeFindex vault shares received
     pub lock_date: u64,              // When funds were locked
     pub payout_date: u64,            // When defindex will distribute
-    pub yield_earned: i128,          // Yield from defindex
-    pub funds_released: bool,        // Released to defindex for distribution
-    pub yield_claimed: bool,         // Employer claimed yield
+    pub yield_earned: i128,          // Withdrawn yield not yet claimed; yield_shares hold the rest (realized_yield keeps the total withdrawn)
+    pub funds_released: bool,        // Derived from status: Released or Claimed (kept for compatibility)
+    pub yield_claimed: bool,         // Derived from status: Claimed (kept for compatibility)
+    pub estimated_yield: i128,       // APY-based estimate recorded at release, for auditing
+    pub realized_yield: i128,        // Yield actually realized from the vault on release
+    pub release_date: u64,           // When funds were released (0 until then)
+    pub assets: Vec<(Address, i128)>, // Amount deposited per vault asset, in vault order (first is the configured token)
+    pub vault: Address,              // DeFindex vault holding this batch's shares
+    pub snapshot_yield: i128,        // Unrealized yield at the last snapshot_yield call
+    pub snapshot_at: u64,            // When snapshot_yield last ran (0 if never)
+    pub label: Option<String>,       // Employer-chosen batch name, e.g. "March 2025 Engineering"
+    pub yield_shares: i128,          // Shares left invested after release for the unclaimed yield (0 once withdrawn)
+    pub withdrawal_requested_at: u64, // When an async vault redemption was requested (0 if never)
+    pub withdrawal_ready_at: u64,    // When the requested redemption can be withdrawn
+    pub platform_fee_bps: Option<u32>, // Employer's negotiated platform fee as of lock time, see set_employer_fee (None = contract-wide fee)
+    pub principal_loss: i128,        // Configured-token shortfall beyond rounding tolerance on release
+    pub status: LockStatus,          // Lifecycle stage, the source of truth for the flags above
+    pub employee_yield: i128,        // Employees' share set aside by claims, paid by distribute_employee_yield
+    pub employee_yield_distributed: bool, // Employee share sent to the SDP wallet
+    pub employer_yield_bps: Option<u32>, // Negotiated employer/employee split of yield after platform fee, see LockOptions
+}
+
+// Lifecycle of a batch: Locked -> Released -> Claimed, or Locked -> Cancelled
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LockStatus {
+    Locked,
+    Released,
+    Claimed,
+    Cancelled,
+}
+
+// What releasing a batch right now would move, for keepers to simulate before submitting
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReleasePreview {
+    pub principal: i128,       // Configured-token principal sent to the destination
+    pub yield_earned: i128,    // Yield the release would leave claimable, before platform fee
+    pub withdraw_shares: i128, // Vault shares the release withdraws
+    pub multi_asset: bool,     // Whether the vault withdrawal returns several assets
+    pub releasable: bool,      // Whether a release would pass the due-date and redemption checks now
+}
+
+// Where release_to_sdp sends a batch's principal
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReleaseDestination {
+    Sdp(Address), // SDP wallet that disburses to employees
+    Employer,     // Back to the employer, for self-distributed payroll
+}
+
+// Contract-wide split of yield in basis points of the whole, summing to 10000
+// (an employer's negotiated fee, see set_employer_fee, replaces the platform fee for its batches)
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SplitConfig {
+    pub employer_bps: u32,
+    pub employee_bps: u32,
+    pub platform_fee_bps: u32,
+}
+
+// How a released batch's yield will be split on claim
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct YieldBreakdown {
+    pub employer_share: i128,
+    pub employee_share: i128, // Employees' portion, see Settings::employee_yield_bps
+    pub platform_fee: i128,
+}
+
+// Running totals of an employer's batches, since events can't be read back on-chain
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EmployerSummary {
+    pub total_locks: u32,
+    pub total_released: u32,
+    pub total_claimed: u32,   // Batches whose yield has been fully claimed
+    pub total_cancelled: u32,
+    pub amount_locked: i128,   // Principal ever locked, net of transfers out and forced partial withdrawals
+    pub amount_released: i128, // Principal ever released
+    pub yield_claimed: i128,   // Yield paid to the employer, net of platform fee
+}
+
+// One page of check_solvency; monitoring sums the pages and compares the totals
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolvencyPage {
+    pub obligations: i128,      // Unreleased principal and unclaimed yield of the page's employers (plus deposits on page 0)
+    pub holdings: i128,         // Value of the shares backing their unreleased batches (plus the contract's balance on page 0)
+    pub next_start: Option<u32>, // Where the next page starts, None after the last employer
+}
+
+// One lifecycle step of a batch, e.g. `locked` then `released` then `claimed`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Transition {
+    pub kind: Symbol,
+    pub timestamp: u64,
 }
 
+// Per-lock and per-employer records live in persistent storage with their own TTL (see `load`);
+// contract-wide configuration and totals stay in instance storage
 #[contracttype]
 pub enum DataKey {
     PayrollLock(Address, u64), // (employer, batch_id)
     DefindexPoolAddress,
     TokenAddress,
     NextBatchId(Address), // Track next batch_id per employer
+    Allocations(Address, u64), // Per-employee splits for (employer, batch_id)
+    PlatformFeeBps,
+    FeeCollector,
+    Admin,
+    MaxTvl,      // Contract-wide cap on locked principal (0 = no cap)
+    TotalValueLocked, // Principal currently locked across all employers
+    Operators,   // Addresses allowed to trigger releases
+    EmployerAgent(Address), // Agent allowed to lock on behalf of an employer
+    Settings,
+    EmployerCount, // Number of employers that have ever had a batch
+    IdemKey(Address, BytesN<32>), // (employer, idempotency key) -> batch_id
+    BatchIdemKey(Address, u64), // (employer, batch_id) -> idempotency key it was locked under
+    SdpWallet(Address), // SDP wallet an employer accepts keeper releases to
+    TokenDecimals, // Cached (token, decimals) for the configured token
+    Blocked(Address), // Employers barred from creating new locks
+    History(Address, u64), // Lifecycle transitions of (employer, batch_id)
+    Frozen, // Set while an emergency freeze halts all fund movements
+    SaltedBatches(Address), // Salt-derived batch ids ever used by an employer
+    Approvals(BytesN<32>), // Admins that approved a pending privileged operation (temporary storage)
+    BatchSdpWallet(Address, u64), // SDP wallet fixed at lock time that keeper releases send (employer, batch_id) to
+    Summary(Address), // Running lifecycle totals of an employer's batches
+    Guardian, // Set at deploy; may correct the initialize parameters once before any lock
+    Reinitialized, // Set once the guardian has used its one-time correction
+    EmployerFeeBps(Address), // Admin-negotiated platform fee applied to an employer's new batches
+    Deposit(Address), // Tokens an employer deposited for `lock_existing_balance` and has not locked yet
+    TotalDeposits, // Sum of all employers' unlocked deposits
+    LossAccepted(Address, u64), // Principal loss admins approved for (employer, batch_id) ahead of a short release
+    Employer(u32), // The i-th employer to get a batch, for paging through them
+    Registered(Address), // Set once an employer is listed under `Employer`
+    HeldShares(Address), // Vault shares held for batches in a vault: unreleased principal plus yield left invested
+    TotalHeldShares, // HeldShares summed over every vault
+    Cancelled(Address, u64), // Compact tombstone of a cancelled (employer, batch_id), kept apart from the full record
+}
+
+// Storage layout of the original single-lock contract, kept for migration
+#[contracttype]
+pub enum LegacyDataKey {
+    PayrollLock,
+}
+
+// Lock as stored by the single-lock contract (one lock per instance, no batch IDs)
+#[contracttype]
+#[derive(Clone)]
+pub struct LegacyPayrollLock {
+    pub employer: Address,
+    pub total_amount: i128,
+    pub vault_shares: i128,
+    pub lock_date: u64,
+    pub payout_date: u64,
+    pub yield_earned: i128,
+    pub funds_released: bool,
+    pub yield_claimed: bool,
 }
 
 #[contracterror]
@@ -91,293 +1014,2664 @@ pub enum Error {
     NotYetReleased = 8,
     InvalidAmount = 9,
     InvalidPayoutDate = 10,
+    InvalidFee = 11,
+    TvlCapExceeded = 12,
+    InsufficientShares = 13,
+    ClaimWindowActive = 14,
+    LockTooShort = 15,
+    TooManyBatches = 16,
+    InvalidAssets = 17,
+    CapExceeded = 18,
+    BatchNotFound = 19,
+    PayoutTooFar = 20,
+    BelowMinimum = 21,
+    CancelWindowClosed = 22,
+    InvalidToken = 23,
+    InvalidLabel = 24,
+    ContractFrozen = 25,
+    BatchIdTaken = 26,
+    InvalidScale = 27,
+    InvalidThreshold = 28,
+    ArithmeticOverflow = 29,
+    WithdrawalNotReady = 30,
+    InvalidStateTransition = 31,
+    AlreadyInUse = 32,
+    InvalidConfig = 33,
+    TimelockActive = 34,
+    LockCancelled = 35,
 }
 
-#[contract]
-pub struct PayrollYieldContract;
+// How `lock_assets` pulls the employer's tokens into the contract
+#[derive(Clone, Copy)]
+enum Funding {
+    // Direct transfer, authorized by the employer's signature
+    Transfer,
+    // `transfer_from` against an allowance the employer granted this contract
+    Allowance,
+    // Tokens the contract already holds on the employer's behalf (e.g. claimed yield)
+    Held,
+}
 
-#[contractimpl]
-impl PayrollYieldContract {
+// Lock `total_amount` of the configured token; see `lock_assets`
+fn lock_funds(
+    env: &Env,
+    employer: &Address,
+    total_amount: i128,
+    payout_date: u64,
+    min_shares: i128,
+    funding: Funding,
+    options: &LockOptions,
+) -> Result<u64, Error> {
+    // Get stored token address
+    let token: Address = env.storage()
+        .instance()
+        .get(&DataKey::TokenAddress)
+        .ok_or(Error::NotInitialized)?;
     
-    /// Initialize contract with defindex Pool address and token
-    pub fn initialize(env: Env, defindex_pool: Address, token: Address) -> Result<(), Error> {
-        // Check if already initialized
-        if env.storage().instance().has(&DataKey::DefindexPoolAddress) {
-            return Err(Error::AlreadyInitialized);
+    lock_assets(env, employer, vec![env, (token, total_amount)], payout_date, min_shares, funding, options)
+}
+
+// Shared locking logic: pull funds from the employer, deposit them into the
+// DeFindex vault and record a new batch. Callers handle authorization.
+// `assets` follows the vault's asset order; the first entry must be the configured
+// token, which is the one principal, TVL and yield are accounted in.
+fn lock_assets(
+    env: &Env,
+    employer: &Address,
+    assets: Vec<(Address, i128)>,
+    payout_date: u64,
+    min_shares: i128,
+    funding: Funding,
+    options: &LockOptions,
+) -> Result<u64, Error> {
+    // Validate amounts
+    for (_, amount) in assets.iter() {
+        check_nonnegative_amount(amount)?;
+    }
+    check_nonnegative_amount(min_shares)?;
+    if let Some(label) = &options.label {
+        if label.is_empty() || label.len() > MAX_LABEL_LEN {
+            return Err(Error::InvalidLabel);
+        }
+    }
+    if options.employer_yield_bps.unwrap_or(0) as i128 > BPS_DENOMINATOR {
+        return Err(Error::InvalidConfig);
+    }
+    
+    // Extend storage TTL
+    bump_instance(env);
+    
+    // Blocked employers can still release and claim existing batches, but not lock more
+    if has_record(env, &DataKey::Blocked(employer.clone())) {
+        return Err(Error::Unauthorized);
+    }
+    
+    // Verify payout date is in the future
+    if payout_date <= env.ledger().timestamp() {
+        return Err(Error::InvalidPayoutDate);
+    }
+    
+    // Enforce the minimum lock duration so yield accrues meaningfully
+    let min_lock_secs = settings(env).min_lock_secs.unwrap_or(0);
+    if payout_date < env.ledger().timestamp().saturating_add(min_lock_secs) {
+        return Err(Error::LockTooShort);
+    }
+    
+    // Don't let funds be trapped behind a payout date decades out
+    let max_horizon = settings(env).max_lock_horizon_secs.unwrap_or(u64::MAX);
+    if payout_date > env.ledger().timestamp().saturating_add(max_horizon) {
+        return Err(Error::PayoutTooFar);
+    }
+    
+    // Get stored token address
+    let token: Address = env.storage()
+        .instance()
+        .get(&DataKey::TokenAddress)
+        .ok_or(Error::NotInitialized)?;
+    
+    // The configured token leads and no asset appears twice
+    let (primary, total_amount) = assets.get(0).ok_or(Error::InvalidAssets)?;
+    if primary != token {
+        return Err(Error::InvalidAssets);
+    }
+    for i in 1..assets.len() {
+        let (asset, _) = assets.get_unchecked(i);
+        if assets.iter().take(i as usize).any(|(other, _)| other == asset) {
+            return Err(Error::InvalidAssets);
+        }
+    }
+    
+    // Enforce the contract-wide TVL cap (0 disables it)
+    let total_locked: i128 = env.storage()
+        .instance()
+        .get(&DataKey::TotalValueLocked)
+        .unwrap_or(0);
+    let new_total_locked = total_locked
+        .checked_add(total_amount)
+        .ok_or(Error::InvalidAmount)?;
+    let max_tvl: i128 = env.storage()
+        .instance()
+        .get(&DataKey::MaxTvl)
+        .unwrap_or(0);
+    if max_tvl > 0 && new_total_locked > max_tvl {
+        return Err(Error::TvlCapExceeded);
+    }
+    
+    // Enforce the per-employer cap on unreleased principal
+    let max_per_employer = settings(env).max_locked_per_employer.unwrap_or(i128::MAX);
+    if employer_locked(env, employer).saturating_add(total_amount) > max_per_employer {
+        return Err(Error::CapExceeded);
+    }
+    
+    // Get next batch_id for this employer, or the salt-derived one (never reusable)
+    let mut salted: Vec<u64> = load(env, &DataKey::SaltedBatches(employer.clone()))
+        .unwrap_or(Vec::new(env));
+    let batch_id: u64 = match &options.salt {
+        Some(salt) => {
+            let batch_id = salted_batch_id(env, employer, salt);
+            if salted.contains(batch_id) {
+                return Err(Error::BatchIdTaken);
+            }
+            batch_id
+        }
+        None => load(env, &DataKey::NextBatchId(employer.clone()))
+            .unwrap_or(0),
+    };
+    
+    // Get DeFindex vault address
+    let defindex_vault: Address = env.storage()
+        .instance()
+        .get(&DataKey::DefindexPoolAddress)
+        .ok_or(Error::NotInitialized)?;
+    
+    let mut balances_before = Vec::new(env);
+    let mut amounts_vec = Vec::new(env);
+    let mut min_amounts = Vec::new(env);
+    let mut auth_entries = Vec::new(env);
+    for (asset, amount) in assets.iter() {
+        // Snapshot our balance so anything the vault doesn't consume can be refunded
+        let token_client = TokenClient::new(env, &asset);
+        let balance = token_client.balance(&env.current_contract_address());
+        
+        // Transfer tokens from employer to contract
+        match funding {
+            Funding::Transfer => token_client.transfer(
+                employer,
+                env.current_contract_address(),
+                &amount,
+            ),
+            Funding::Allowance => token_client.transfer_from(
+                &env.current_contract_address(),
+                employer,
+                &env.current_contract_address(),
+                &amount,
+            ),
+            Funding::Held => {}
+        }
+        
+        // Held funds are already part of our balance, so exclude them from the snapshot
+        let balance_before = match funding {
+            Funding::Held => balance.checked_sub(amount).ok_or(Error::InvalidAmount)?,
+            _ => balance,
+        };
+        balances_before.push_back(balance_before);
+        
+        // Deposit what actually arrived; fee-on-transfer tokens deliver less than `amount`
+        let received = token_client
+            .balance(&env.current_contract_address())
+            .checked_sub(balance_before)
+            .ok_or(Error::InvalidAmount)?;
+        amounts_vec.push_back(received);
+        // Multi-asset vaults may take less than offered to keep their ratio; the residual is
+        // refunded below and `min_shares` guards what the employer actually gets
+        min_amounts.push_back(0);
+        
+        // Authorize the token transfer that DeFindex will make
+        auth_entries.push_back(InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: asset.clone(),
+                fn_name: Symbol::new(env, "transfer"),
+                args: (
+                    env.current_contract_address(),
+                    defindex_vault.clone(),
+                    received,
+                ).into_val(env),
+            },
+            sub_invocations: vec![env],
+        }));
+    }
+    env.authorize_as_current_contract(auth_entries);
+    
+    // Now call deposit - the authorization above allows DeFindex to transfer our tokens
+    let defindex_client = DefindexVaultClient::new(env, &defindex_vault);
+    let (_, vault_shares, _) = defindex_client.deposit(
+        &amounts_vec,
+        &min_amounts,
+        &env.current_contract_address(),
+        &options.invest.unwrap_or(true),
+    );
+    
+    // Protect the employer from vaults that mint fewer shares than expected; a deposit that
+    // rounds down to no shares at all would strand the funds in a batch that can't release
+    if vault_shares <= 0 || vault_shares < min_shares {
+        return Err(Error::InsufficientShares);
+    }
+    
+    // Refund any residual the vault didn't consume and lock only what was deposited
+    let mut deposited = Vec::new(env);
+    for (i, (asset, _)) in assets.iter().enumerate() {
+        let token_client = TokenClient::new(env, &asset);
+        let residual = token_client
+            .balance(&env.current_contract_address())
+            .checked_sub(balances_before.get_unchecked(i as u32))
+            .unwrap_or(0)
+            .max(0);
+        if residual > 0 {
+            token_client.transfer(&env.current_contract_address(), employer, &residual);
+        }
+        deposited.push_back((asset, amounts_vec.get_unchecked(i as u32) - residual));
+    }
+    let (_, locked_amount) = deposited.get_unchecked(0);
+    
+    let lock = PayrollLock {
+        employer: employer.clone(),
+        total_amount: locked_amount,
+        vault_shares,
+        lock_date: env.ledger().timestamp(),
+        payout_date,
+        yield_earned: 0,
+        funds_released: false,
+        yield_claimed: false,
+        estimated_yield: 0,
+        realized_yield: 0,
+        release_date: 0,
+        assets: deposited,
+        vault: defindex_vault,
+        snapshot_yield: 0,
+        snapshot_at: 0,
+        label: options.label.clone(),
+        yield_shares: 0,
+        withdrawal_requested_at: 0,
+        withdrawal_ready_at: 0,
+        platform_fee_bps: load(env, &DataKey::EmployerFeeBps(employer.clone())),
+        principal_loss: 0,
+        status: LockStatus::Locked,
+        employee_yield: 0,
+        employee_yield_distributed: false,
+        employer_yield_bps: options.employer_yield_bps,
+    };
+    
+    save_lock(env, &DataKey::PayrollLock(employer.clone(), batch_id), &lock);
+    
+    // Increment batch_id for next lock, or retire the salted id
+    if options.salt.is_some() {
+        salted.push_back(batch_id);
+        store(env, &DataKey::SaltedBatches(employer.clone()), &salted);
+        register_employer(env, employer);
+    } else {
+        store(env, &DataKey::NextBatchId(employer.clone()), &(batch_id + 1));
+        if batch_id == 0 {
+            register_employer(env, employer);
+        }
+    }
+    env.storage().instance().set(&DataKey::TotalValueLocked, &(total_locked + locked_amount));
+    
+    update_summary(env, employer, |summary| {
+        summary.total_locks += 1;
+        summary.amount_locked += locked_amount;
+    });
+    record_transition(env, employer, batch_id, symbol_short!("locked"));
+    env.events().publish((symbol_short!("locked"), batch_id), employer.clone());
+    
+    Ok(batch_id)
+}
+
+// Helper function to check a batch can be released now: due, not yet released, backed by
+// vault shares and not waiting on an async redemption
+fn check_releasable(env: &Env, lock: &PayrollLock) -> Result<(), Error> {
+    check_not_cancelled(lock)?;
+    
+    // Verify payout date has been reached
+    if env.ledger().timestamp() < lock.payout_date {
+        return Err(Error::PayoutDateNotReached);
+    }
+    
+    // Verify funds haven't already been released
+    if lock.funds_released {
+        return Err(Error::AlreadyReleased);
+    }
+    
+    // A lock that never got shares has nothing to withdraw; don't pay out principal we don't hold
+    if lock.vault_shares <= 0 {
+        return Err(Error::InsufficientShares);
+    }
+    
+    // A pending async redemption can't be withdrawn before the vault's delay
+    if lock.withdrawal_requested_at > 0 && env.ledger().timestamp() < lock.withdrawal_ready_at {
+        return Err(Error::WithdrawalNotReady);
+    }
+    Ok(())
+}
+
+// Helper function to work out a release without writing any state: returns the batch as
+// it will be stored once released, and the vault shares to withdraw. Every check that can
+// fail before the vault is called fails here
+fn prepare_release(env: &Env, employer: &Address, batch_id: u64) -> Result<(PayrollLock, i128), Error> {
+    let mut lock: PayrollLock = load(env, &DataKey::PayrollLock(employer.clone(), batch_id))
+        .ok_or(Error::BatchNotFound)?;
+    check_releasable(env, &lock)?;
+    
+    let withdraw_shares = release_shares(env, &lock)?;
+    lock.yield_shares = lock.vault_shares - withdraw_shares;
+    lock.estimated_yield = estimate_yield(env, &lock, env.ledger().timestamp())?;
+    set_status(&mut lock, LockStatus::Released)?;
+    lock.release_date = env.ledger().timestamp();
+    Ok((lock, withdraw_shares))
+}
+
+// Release a due batch and send its principal to a single SDP wallet
+fn release_to_wallet(
+    env: &Env,
+    employer: &Address,
+    batch_id: u64,
+    destination: &ReleaseDestination,
+) -> Result<i128, Error> {
+    let (lock, principal_out) = withdraw_lock(env, employer, batch_id)?;
+    let yield_earned = unclaimed_yield(env, &lock);
+    let wallet = match destination {
+        ReleaseDestination::Sdp(sdp_wallet_address) => sdp_wallet_address,
+        ReleaseDestination::Employer => employer,
+    };
+    
+    // Transfer principal of every asset to the destination wallet for distribution
+    for (asset, amount) in principal_out.iter() {
+        if amount > 0 {
+            TokenClient::new(env, &asset).transfer(
+                &env.current_contract_address(),
+                wallet,
+                &amount,
+            );
+        }
+    }
+    
+    env.events().publish(
+        (symbol_short!("released"), batch_id, yield_earned), 
+        wallet.clone()
+    );
+    
+    // Signal SDP to start disbursing, indexed by the receiving wallet
+    if let ReleaseDestination::Sdp(sdp_wallet_address) = destination {
+        let (_, principal) = principal_out.get_unchecked(0);
+        env.events().publish(
+            (symbol_short!("sdp_ready"), sdp_wallet_address.clone()),
+            (batch_id, principal, employer.clone()),
+        );
+    }
+    Ok(yield_earned)
+}
+
+// Shared release logic: withdraw a due batch from the DeFindex vault, record the
+// realized yield and mark it released. Returns the updated lock and the principal
+// available to pay out per asset (configured token first); callers handle
+// authorization and the outgoing principal transfers.
+fn withdraw_lock(env: &Env, employer: &Address, batch_id: u64) -> Result<(PayrollLock, Vec<(Address, i128)>), Error> {
+    // Extend storage TTL
+    bump_instance(env);
+    
+    let (mut lock, withdraw_shares) = prepare_release(env, employer, batch_id)?;
+    
+    // Mark the batch released before calling out, so a re-entrant release of the same
+    // batch sees it as already released (checks-effects-interactions)
+    let key = DataKey::PayrollLock(employer.clone(), batch_id);
+    save_lock(env, &key, &lock);
+    
+    // Principal is no longer locked
+    decrease_tvl(env, lock.total_amount);
+    update_summary(env, employer, |summary| {
+        summary.total_released += 1;
+        summary.amount_released += lock.total_amount;
+    });
+    record_transition(env, employer, batch_id, symbol_short!("released"));
+    
+    // Withdraw from the DeFindex vault the batch was deposited into
+    let defindex_client = DefindexVaultClient::new(env, &lock.vault);
+    // No minimum here: shortfalls are classified below instead of reverting
+    let mut min_amounts_out = Vec::new(env);
+    for _ in lock.assets.iter() {
+        min_amounts_out.push_back(0);
+    }
+    
+    let withdrawn_amounts = defindex_client.withdraw(
+        &withdraw_shares,
+        &min_amounts_out,
+        &env.current_contract_address(),
+    );
+    
+    // An empty result means nothing came back; never pay out principal we don't hold
+    let total_withdrawn = withdrawn_amounts.get(0).ok_or(Error::InsufficientFunds)?;
+    
+    // Calculate yield withdrawn with the principal (or principal lost beyond rounding dust)
+    // and record it alongside the estimate
+    let (yield_earned, principal_loss) = split_withdrawal(env, lock.total_amount, total_withdrawn);
+    lock.realized_yield = yield_earned;
+    lock.yield_earned = yield_earned;
+    lock.principal_loss = principal_loss;
+    save_lock(env, &key, &lock);
+    if principal_loss > 0 {
+        // A real shortfall reverts unless admins accepted at least this much loss for the batch
+        let accepted_key = DataKey::LossAccepted(employer.clone(), batch_id);
+        let accepted: i128 = load(env, &accepted_key).unwrap_or(0);
+        if principal_loss > accepted {
+            return Err(Error::InsufficientFunds);
+        }
+        drop_record(env, &accepted_key);
+        env.events().publish((symbol_short!("loss"), batch_id), (employer.clone(), principal_loss));
+    }
+    
+    // Never pay out more principal than was actually withdrawn for this batch
+    let mut principal_out = vec![env, (lock.assets.get_unchecked(0).0, total_withdrawn.min(lock.total_amount))];
+    
+    // `yield_earned` is denominated in the configured token, so growth on the other
+    // assets goes straight back to the employer, and a drop in them is passed on as is
+    for (i, (asset, amount)) in lock.assets.iter().enumerate().skip(1) {
+        let withdrawn = withdrawn_amounts.get(i as u32).ok_or(Error::InsufficientFunds)?;
+        let (surplus, _) = split_withdrawal(env, amount, withdrawn);
+        if surplus > 0 {
+            TokenClient::new(env, &asset).transfer(&env.current_contract_address(), employer, &surplus);
+        }
+        principal_out.push_back((asset, withdrawn.min(amount)));
+    }
+    
+    Ok((lock, principal_out))
+}
+
+// Cancel an unreleased batch and refund everything the vault returns, yield included and
+// less the platform fee on that yield, to `refund_to`. Returns the configured-token amount
+// refunded. Callers handle authorization and the timing checks.
+fn cancel_batch(
+    env: &Env,
+    employer: &Address,
+    batch_id: u64,
+    mut lock: PayrollLock,
+    refund_to: &Address,
+) -> Result<i128, Error> {
+    // Mark the batch cancelled before calling out so it can't be released or cancelled
+    // again; the record stays for auditing
+    set_status(&mut lock, LockStatus::Cancelled)?;
+    save_lock(env, &DataKey::PayrollLock(employer.clone(), batch_id), &lock);
+    store(env, &DataKey::Cancelled(employer.clone(), batch_id), &true);
+    drop_record(env, &DataKey::Allocations(employer.clone(), batch_id));
+    drop_record(env, &DataKey::BatchSdpWallet(employer.clone(), batch_id));
+    decrease_tvl(env, lock.total_amount);
+    
+    let mut refunded: i128 = 0;
+    if lock.vault_shares > 0 {
+        let mut min_amounts_out = Vec::new(env);
+        for _ in lock.assets.iter() {
+            min_amounts_out.push_back(0);
+        }
+        
+        let withdrawn_amounts = DefindexVaultClient::new(env, &lock.vault).withdraw(
+            &lock.vault_shares,
+            &min_amounts_out,
+            &env.current_contract_address(),
+        );
+        
+        // Yield earned on the configured token pays the platform fee, as it would on claim
+        let withdrawn = withdrawn_amounts.get(0).unwrap_or(0);
+        let fee = collect_platform_fee(env, &lock, batch_id, withdrawn - lock.total_amount)?;
+        
+        // Return every asset the vault gave back, yield included
+        for (i, (asset, _)) in lock.assets.iter().enumerate() {
+            let mut amount = withdrawn_amounts.get(i as u32).unwrap_or(0);
+            if i == 0 {
+                amount -= fee;
+                refunded = amount;
+            }
+            if amount > 0 {
+                TokenClient::new(env, &asset).transfer(&env.current_contract_address(), refund_to, &amount);
+            }
+        }
+    }
+    
+    update_summary(env, employer, |summary| summary.total_cancelled += 1);
+    record_transition(env, employer, batch_id, symbol_short!("cancelled"));
+    Ok(refunded)
+}
+
+// Pay out `amount` of a released batch's remaining yield (all of it when None):
+// platform fee to the collector, the rest to the employer. With `pay_out` false the
+// employer's share stays in the contract for the caller to use. Callers handle authorization.
+fn claim_lock_yield(
+    env: &Env,
+    employer: &Address,
+    batch_id: u64,
+    amount: Option<i128>,
+    pay_out: bool,
+) -> Result<i128, Error> {
+    // Extend storage TTL
+    bump_instance(env);
+    
+    let mut lock: PayrollLock = load(env, &DataKey::PayrollLock(employer.clone(), batch_id))
+        .ok_or(Error::BatchNotFound)?;
+    
+    // Verify caller is the employer who locked the funds
+    if lock.employer != *employer {
+        return Err(Error::Unauthorized);
+    }
+    
+    check_not_cancelled(&lock)?;
+    
+    // Verify funds have been released to defindex (FIXED BUG)
+    if !lock.funds_released {
+        return Err(Error::NotYetReleased);
+    }
+    
+    // Verify yield hasn't already been claimed
+    if lock.yield_claimed {
+        return Err(Error::AlreadyClaimed);
+    }
+    
+    // Collect the yield still invested at its current value
+    let key = DataKey::PayrollLock(employer.clone(), batch_id);
+    redeem_yield_shares(env, &key, &mut lock);
+    
+    // Yield is paid in the batch's own token
+    let token = lock_token(&lock);
+    
+    // Never claim more than the yield still unclaimed
+    let amount = amount.unwrap_or(lock.yield_earned);
+    if amount > lock.yield_earned {
+        return Err(Error::InsufficientFunds);
+    }
+    
+    // Calculate platform fee, the employees' share set aside for distribution and employer's share
+    let fee = platform_fee(env, &lock, amount)?;
+    let employee_yield = employee_share(env, &lock, amount - fee)?;
+    let employer_share = amount - fee - employee_yield;
+    
+    // Skip micro-claims that aren't worth the transfer; dust batches are closed out by
+    // sweep_unclaimed_yield once the claim window passes
+    if pay_out && employer_share > 0
+        && employer_share < settings(env).min_claimable_yield.unwrap_or(0)
+    {
+        return Err(Error::BelowMinimum);
+    }
+    
+    // Record the claim before any transfer; it's marked claimed once nothing is left
+    lock.yield_earned -= amount;
+    lock.employee_yield += employee_yield;
+    if lock.yield_earned == 0 {
+        set_status(&mut lock, LockStatus::Claimed)?;
+    }
+    save_lock(env, &key, &lock);
+    
+    let token_client = TokenClient::new(env, &token);
+    
+    // Transfer platform fee to the fee collector first
+    if fee > 0 {
+        let fee_collector: Address = env.storage()
+            .instance()
+            .get(&DataKey::FeeCollector)
+            .ok_or(Error::NotInitialized)?;
+        
+        token_client.transfer(
+            &env.current_contract_address(),
+            &fee_collector,
+            &fee,
+        );
+        
+        env.events().publish((symbol_short!("fee"), batch_id), (fee_collector, fee));
+    }
+    
+    // Transfer yield to employer (skip zero transfers, some tokens reject them)
+    if pay_out && employer_share > 0 {
+        token_client.transfer(
+            &env.current_contract_address(),
+            employer,
+            &employer_share,
+        );
+    }
+    
+    let fully_claimed = lock.status == LockStatus::Claimed;
+    update_summary(env, employer, |summary| {
+        summary.total_claimed += fully_claimed as u32;
+        summary.yield_claimed += employer_share;
+    });
+    record_transition(env, employer, batch_id, symbol_short!("claimed"));
+    env.events().publish((symbol_short!("yield"), batch_id), employer.clone());
+    Ok(employer_share)
+}
+
+#[contract]
+pub struct PayrollYieldContract;
+
+#[contractimpl]
+impl PayrollYieldContract {
+    
+    /// Deploy with the guardian allowed a one-time `reinitialize` before any lock exists
+    pub fn __constructor(env: Env, guardian: Address) {
+        env.storage().instance().set(&DataKey::Guardian, &guardian);
+    }
+    
+    /// Initialize contract with defindex Pool address, admin and platform fee settings
+    /// The payroll token is the vault's first asset
+    /// `platform_fee_bps` is the platform's cut of yield in basis points (max 1000 = 10%)
+    /// `settings` holds optional knobs; unset fields keep their defaults
+    pub fn initialize(
+        env: Env,
+        defindex_pool: Address,
+        admin: Address,
+        platform_fee_bps: u32,
+        fee_collector: Address,
+        settings: Settings,
+    ) -> Result<(), Error> {
+        // Check if already initialized
+        if env.storage().instance().has(&DataKey::DefindexPoolAddress) {
+            return Err(Error::AlreadyInitialized);
+        }
+        
+        let token = configure(&env, &defindex_pool, &admin, platform_fee_bps, &fee_collector, &settings)?;
+        
+        // Only reached on first initialization, re-init returns AlreadyInitialized above
+        env.events().publish((symbol_short!("init"),), (defindex_pool, token, admin));
+        
+        Ok(())
+    }
+    
+    /// Guardian replaces the initialize parameters once, to correct a misconfigured deployment
+    /// Only allowed before any lock or deposit exists (AlreadyInUse otherwise); operators, caps and other
+    /// state set after initialize are kept
+    pub fn reinitialize(
+        env: Env,
+        defindex_pool: Address,
+        admin: Address,
+        platform_fee_bps: u32,
+        fee_collector: Address,
+        settings: Settings,
+    ) -> Result<(), Error> {
+        let guardian: Address = env.storage()
+            .instance()
+            .get(&DataKey::Guardian)
+            .ok_or(Error::Unauthorized)?;
+        guardian.require_auth();
+        
+        if !env.storage().instance().has(&DataKey::DefindexPoolAddress) {
+            return Err(Error::NotInitialized);
+        }
+        if env.storage().instance().has(&DataKey::Reinitialized) {
+            return Err(Error::AlreadyInitialized);
+        }
+        
+        let token = configure(&env, &defindex_pool, &admin, platform_fee_bps, &fee_collector, &settings)?;
+        env.storage().instance().set(&DataKey::Reinitialized, &true);
+        
+        env.events().publish((symbol_short!("reinit"),), (defindex_pool, token, admin));
+        
+        Ok(())
+    }
+    
+    /// Admin migrates a lock stored in the legacy single-lock layout to the batch model
+    /// Idempotent: returns the new batch_id, or None if there is no legacy lock left
+    pub fn migrate_legacy_lock(env: Env) -> Result<Option<u64>, Error> {
+        require_admin(&env)?;
+        bump_instance(&env);
+        
+        let legacy: LegacyPayrollLock = match env.storage().instance().get(&LegacyDataKey::PayrollLock) {
+            Some(legacy) => legacy,
+            None => return Ok(None),
+        };
+        
+        // Get stored token and DeFindex vault addresses
+        let token: Address = env.storage()
+            .instance()
+            .get(&DataKey::TokenAddress)
+            .ok_or(Error::NotInitialized)?;
+        let defindex_vault: Address = env.storage()
+            .instance()
+            .get(&DataKey::DefindexPoolAddress)
+            .ok_or(Error::NotInitialized)?;
+        
+        // Normally batch 0; never overwrite batches the employer already has
+        let batch_id: u64 = load(&env, &DataKey::NextBatchId(legacy.employer.clone()))
+            .unwrap_or(0);
+        
+        let lock = PayrollLock {
+            employer: legacy.employer.clone(),
+            total_amount: legacy.total_amount,
+            vault_shares: legacy.vault_shares,
+            lock_date: legacy.lock_date,
+            payout_date: legacy.payout_date,
+            yield_earned: legacy.yield_earned,
+            funds_released: legacy.funds_released,
+            yield_claimed: legacy.yield_claimed,
+            estimated_yield: 0,
+            realized_yield: if legacy.funds_released { legacy.yield_earned } else { 0 },
+            release_date: 0,
+            assets: vec![&env, (token, legacy.total_amount)],
+            vault: defindex_vault,
+            snapshot_yield: 0,
+            snapshot_at: 0,
+            label: None,
+            yield_shares: 0,
+            withdrawal_requested_at: 0,
+            withdrawal_ready_at: 0,
+            platform_fee_bps: None,
+            principal_loss: 0,
+            status: match (legacy.funds_released, legacy.yield_claimed) {
+                (true, true) => LockStatus::Claimed,
+                (true, false) => LockStatus::Released,
+                _ => LockStatus::Locked,
+            },
+            employee_yield: 0,
+            employee_yield_distributed: false,
+            employer_yield_bps: None,
+        };
+        
+        save_lock(&env, &DataKey::PayrollLock(legacy.employer.clone(), batch_id), &lock);
+        store(&env, &DataKey::NextBatchId(legacy.employer.clone()), &(batch_id + 1));
+        if batch_id == 0 {
+            register_employer(&env, &legacy.employer);
+        }
+        env.storage().instance().remove(&LegacyDataKey::PayrollLock);
+        update_summary(&env, &legacy.employer, |summary| {
+            summary.total_locks += 1;
+            summary.amount_locked += lock.total_amount;
+            if lock.funds_released {
+                summary.total_released += 1;
+                summary.amount_released += lock.total_amount;
+            }
+            summary.total_claimed += (lock.status == LockStatus::Claimed) as u32;
+        });
+        
+        // Still-locked principal counts towards TVL
+        if !lock.funds_released {
+            let tvl: i128 = env.storage()
+                .instance()
+                .get(&DataKey::TotalValueLocked)
+                .unwrap_or(0);
+            env.storage().instance().set(&DataKey::TotalValueLocked, &(tvl + lock.total_amount));
+        }
+        
+        record_transition(&env, &legacy.employer, batch_id, symbol_short!("migrated"));
+        env.events().publish((symbol_short!("migrated"), batch_id), legacy.employer);
+        Ok(Some(batch_id))
+    }
+    
+    /// Permissionless liveness probe for monitoring: extends the instance TTL, emits a
+    /// `heartbeat` event and returns the current ledger timestamp
+    pub fn heartbeat(env: Env) -> u64 {
+        bump_instance(&env);
+        
+        let now = env.ledger().timestamp();
+        env.events().publish((symbol_short!("heartbeat"),), now);
+        now
+    }
+    
+    /// Contract ABI version so clients can gate behaviour on the deployed code
+    pub fn version(_env: Env) -> u32 {
+        CONTRACT_VERSION
+    }
+    
+    /// Extend the instance TTL so an idle contract doesn't expire
+    /// Permissionless, so a keeper can keep the contract alive cheaply
+    pub fn bump_ttl(env: Env) {
+        bump_instance(&env);
+    }
+    
+    /// Extend the TTL of a batch's records (the lock, its history, allocations and SDP wallet)
+    /// and of the employer's batch counter, so a long-dated lock isn't archived while idle
+    /// Permissionless, like `bump_ttl`
+    pub fn bump_lock_ttl(env: Env, employer: Address, batch_id: u64) -> Result<(), Error> {
+        bump_instance(&env);
+        
+        if !has_record(&env, &DataKey::PayrollLock(employer.clone(), batch_id)) {
+            return Err(Error::BatchNotFound);
+        }
+        for key in [
+            DataKey::PayrollLock(employer.clone(), batch_id),
+            DataKey::History(employer.clone(), batch_id),
+            DataKey::Allocations(employer.clone(), batch_id),
+            DataKey::BatchSdpWallet(employer.clone(), batch_id),
+            DataKey::Cancelled(employer.clone(), batch_id),
+            DataKey::NextBatchId(employer.clone()),
+            DataKey::Summary(employer),
+        ] {
+            if has_record(&env, &key) {
+                env.storage()
+                    .persistent()
+                    .extend_ttl(&key, RECORD_LIFETIME_THRESHOLD, RECORD_BUMP_AMOUNT);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Admins set the contract-wide cap on locked principal (0 disables the cap)
+    /// Privileged: returns true once the admin threshold is met and the cap applies
+    pub fn set_max_tvl(env: Env, approver: Address, max_tvl: i128) -> Result<bool, Error> {
+        check_nonnegative_amount(max_tvl)?;
+        
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("max_tvl"), max_tvl).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        env.storage().instance().set(&DataKey::MaxTvl, &max_tvl);
+        Ok(true)
+    }
+    
+    /// Admins repoint future locks at a new DeFindex vault holding the same token
+    /// Existing locks keep their shares in, and release from, the vault they were locked into
+    /// Privileged: each admin calls with the same vault; returns true once the threshold is met and the switch happens
+    pub fn set_defindex_vault(env: Env, approver: Address, new_vault: Address) -> Result<bool, Error> {
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("set_vault"), new_vault.clone()).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        // Get stored token address
+        let token: Address = env.storage()
+            .instance()
+            .get(&DataKey::TokenAddress)
+            .ok_or(Error::NotInitialized)?;
+        if vault_token(&env, &new_vault)? != token {
+            return Err(Error::InvalidAssets);
+        }
+        
+        env.storage().instance().set(&DataKey::DefindexPoolAddress, &new_vault);
+        env.events().publish((symbol_short!("vault"),), new_vault);
+        Ok(true)
+    }
+    
+    /// Admins negotiate an employer's platform fee (at most 10%), or restore the contract-wide
+    /// fee with `None`; applies to batches the employer locks from now on
+    /// Privileged: each admin calls with the same arguments; returns true once the threshold is met
+    pub fn set_employer_fee(
+        env: Env,
+        approver: Address,
+        employer: Address,
+        fee_bps: Option<u32>,
+    ) -> Result<bool, Error> {
+        bump_instance(&env);
+        
+        if fee_bps.unwrap_or(0) > MAX_PLATFORM_FEE_BPS {
+            return Err(Error::InvalidFee);
+        }
+        
+        let operation = (symbol_short!("emp_fee"), employer.clone(), fee_bps).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        match fee_bps {
+            Some(fee_bps) => store(&env, &DataKey::EmployerFeeBps(employer.clone()), &fee_bps),
+            None => drop_record(&env, &DataKey::EmployerFeeBps(employer.clone())),
+        }
+        env.events().publish((symbol_short!("emp_fee"), employer), fee_bps);
+        Ok(true)
+    }
+    
+    /// Admins accept up to `max_loss` of principal lost on a batch, so its release pays out what
+    /// the vault returns and records the loss instead of reverting with `InsufficientFunds`
+    /// Privileged: each admin calls with the same arguments; returns true once the threshold is met
+    pub fn accept_principal_loss(
+        env: Env,
+        approver: Address,
+        employer: Address,
+        batch_id: u64,
+        max_loss: i128,
+    ) -> Result<bool, Error> {
+        check_nonnegative_amount(max_loss)?;
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("loss_ok"), employer.clone(), batch_id, max_loss).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        store(&env, &DataKey::LossAccepted(employer.clone(), batch_id), &max_loss);
+        env.events().publish((symbol_short!("loss_ok"), batch_id), (employer, max_loss));
+        Ok(true)
+    }
+    
+    /// Get an employer's negotiated platform fee (None if it pays the contract-wide fee)
+    pub fn get_employer_fee(env: Env, employer: Address) -> Option<u32> {
+        bump_instance(&env);
+        
+        load(&env, &DataKey::EmployerFeeBps(employer))
+    }
+    
+    /// DeFindex vault that new locks deposit into
+    pub fn get_defindex_vault(env: Env) -> Result<Address, Error> {
+        bump_instance(&env);
+        
+        env.storage()
+            .instance()
+            .get(&DataKey::DefindexPoolAddress)
+            .ok_or(Error::NotInitialized)
+    }
+    
+    /// Admins add an address to the release operator allowlist
+    /// Privileged: returns true once the admin threshold is met and the operator is added
+    pub fn add_operator(env: Env, approver: Address, operator: Address) -> Result<bool, Error> {
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("add_op"), operator.clone()).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        let mut operators: Vec<Address> = env.storage()
+            .instance()
+            .get(&DataKey::Operators)
+            .unwrap_or(Vec::new(&env));
+        if !operators.contains(&operator) {
+            operators.push_back(operator);
+            env.storage().instance().set(&DataKey::Operators, &operators);
+        }
+        Ok(true)
+    }
+    
+    /// Admins remove an address from the release operator allowlist
+    /// Privileged: returns true once the admin threshold is met and the operator is removed
+    pub fn remove_operator(env: Env, approver: Address, operator: Address) -> Result<bool, Error> {
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("rm_op"), operator.clone()).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        let mut operators: Vec<Address> = env.storage()
+            .instance()
+            .get(&DataKey::Operators)
+            .unwrap_or(Vec::new(&env));
+        if let Some(index) = operators.first_index_of(&operator) {
+            operators.remove(index);
+            env.storage().instance().set(&DataKey::Operators, &operators);
+        }
+        Ok(true)
+    }
+    
+    /// Get the release operator allowlist
+    pub fn get_operators(env: Env) -> Vec<Address> {
+        bump_instance(&env);
+        
+        env.storage()
+            .instance()
+            .get(&DataKey::Operators)
+            .unwrap_or(Vec::new(&env))
+    }
+    
+    /// Check whether an address is the admin or a co-admin (false if not initialized), for
+    /// gating admin UI
+    pub fn is_admin(env: Env, addr: Address) -> bool {
+        bump_instance(&env);
+        
+        admin_set(&env).is_ok_and(|(admins, _)| admins.contains(&addr))
+    }
+    
+    /// Admins block an address from creating new locks (existing batches are unaffected)
+    /// Privileged: returns true once the admin threshold is met and the block applies
+    pub fn block_address(env: Env, approver: Address, addr: Address) -> Result<bool, Error> {
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("block"), addr.clone()).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        store(&env, &DataKey::Blocked(addr.clone()), &true);
+        env.events().publish((symbol_short!("blocked"),), addr);
+        Ok(true)
+    }
+    
+    /// Admins lift a block placed with `block_address`
+    /// Privileged: returns true once the admin threshold is met and the block is lifted
+    pub fn unblock_address(env: Env, approver: Address, addr: Address) -> Result<bool, Error> {
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("unblock"), addr.clone()).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        drop_record(&env, &DataKey::Blocked(addr.clone()));
+        env.events().publish((symbol_short!("unblocked"),), addr);
+        Ok(true)
+    }
+    
+    /// Admins freeze the contract after a confirmed exploit: every lock, release, claim and
+    /// sweep fails with `ContractFrozen` until `unfreeze`. Admin configuration stays available
+    /// Privileged: returns true once the admin threshold is met and the freeze takes effect
+    pub fn freeze(env: Env, approver: Address) -> Result<bool, Error> {
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("freeze"),).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        env.storage().instance().set(&DataKey::Frozen, &true);
+        env.events().publish((symbol_short!("frozen"),), approver);
+        Ok(true)
+    }
+    
+    /// Admins lift a freeze placed with `freeze` (privileged, like `freeze`)
+    pub fn unfreeze(env: Env, approver: Address) -> Result<bool, Error> {
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("unfreeze"),).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(false);
+        }
+        
+        env.storage().instance().remove(&DataKey::Frozen);
+        env.events().publish((symbol_short!("unfrozen"),), approver);
+        Ok(true)
+    }
+    
+    /// Get the admins that can approve privileged operations and how many approvals each needs
+    pub fn get_admins(env: Env) -> Result<(Vec<Address>, u32), Error> {
+        bump_instance(&env);
+        
+        admin_set(&env)
+    }
+    
+    /// Employer authorizes an agent (e.g. a payroll provider) to lock on their behalf
+    /// Agent locks draw from the allowance the employer grants this contract on the token
+    pub fn authorize_agent(env: Env, employer: Address, agent: Address) -> Result<(), Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        bump_instance(&env);
+        
+        store(&env, &DataKey::EmployerAgent(employer), &agent);
+        Ok(())
+    }
+    
+    /// Employer revokes their agent
+    pub fn revoke_agent(env: Env, employer: Address) -> Result<(), Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        bump_instance(&env);
+        
+        drop_record(&env, &DataKey::EmployerAgent(employer));
+        Ok(())
+    }
+    
+    /// Employer sets the SDP wallet that keepers may release their batches to
+    pub fn set_sdp_wallet(env: Env, employer: Address, sdp_wallet_address: Address) -> Result<(), Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        bump_instance(&env);
+        
+        store(&env, &DataKey::SdpWallet(employer), &sdp_wallet_address);
+        Ok(())
+    }
+    
+    /// Employer locks funds for payroll (before sending to defindex)
+    /// Reverts with `InsufficientShares` if the vault mints no shares or fewer than `min_shares`
+    /// When `operator` is set, that registered agent authorizes the lock instead of the
+    /// employer and funds are pulled from the employer's allowance to this contract
+    /// `options` holds optional knobs (idempotency key, invest flag); unset fields keep their defaults
+    pub fn lock_payroll(
+        env: Env,
+        employer: Address,
+        total_amount: i128,
+        payout_date: u64,
+        min_shares: i128,
+        operator: Option<Address>,
+        options: LockOptions,
+    ) -> Result<u64, Error> {
+        require_not_frozen(&env)?;
+        
+        let funding = match operator {
+            Some(operator) => {
+                operator.require_auth();
+                
+                let agent: Option<Address> = load(&env, &DataKey::EmployerAgent(employer.clone()));
+                if agent != Some(operator) {
+                    return Err(Error::Unauthorized);
+                }
+                Funding::Allowance
+            }
+            None => {
+                employer.require_auth();
+                Funding::Transfer
+            }
+        };
+        
+        // A retried lock resolves to the batch created by the first attempt
+        if let Some(key) = &options.idempotency_key {
+            let existing: Option<u64> = load(&env, &DataKey::IdemKey(employer.clone(), key.clone()));
+            if let Some(batch_id) = existing {
+                return Ok(batch_id);
+            }
+        }
+        
+        let batch_id = lock_funds(&env, &employer, total_amount, payout_date, min_shares, funding, &options)?;
+        
+        if let Some(key) = options.idempotency_key {
+            store(&env, &DataKey::IdemKey(employer.clone(), key.clone()), &batch_id);
+            store(&env, &DataKey::BatchIdemKey(employer, batch_id), &key);
+        }
+        
+        Ok(batch_id)
+    }
+    
+    /// Lock funds under a private batch id derived from sha256(employer || salt) instead of
+    /// the guessable sequential counter. Each salt can be used once per employer
+    pub fn lock_payroll_with_id(
+        env: Env,
+        employer: Address,
+        total_amount: i128,
+        payout_date: u64,
+        salt: BytesN<32>,
+    ) -> Result<u64, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        let options = LockOptions { salt: Some(salt), ..Default::default() };
+        lock_funds(&env, &employer, total_amount, payout_date, 0, Funding::Transfer, &options)
+    }
+    
+    /// Lock funds and record per-employee allocations in a single transaction
+    /// The allocation amounts must add up exactly to `total_amount`
+    pub fn lock_with_allocations(
+        env: Env,
+        employer: Address,
+        total_amount: i128,
+        payout_date: u64,
+        min_shares: i128,
+        allocations: Vec<(Address, i128)>,
+    ) -> Result<u64, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        // Validate allocations sum to the locked amount
+        let mut allocated: i128 = 0;
+        for (_, amount) in allocations.iter() {
+            check_nonnegative_amount(amount)?;
+            allocated = allocated
+                .checked_add(amount)
+                .ok_or(Error::InvalidAmount)?;
+        }
+        if allocated != total_amount {
+            return Err(Error::InvalidAmount);
+        }
+        
+        let batch_id = lock_funds(
+            &env,
+            &employer,
+            total_amount,
+            payout_date,
+            min_shares,
+            Funding::Transfer,
+            &LockOptions::default(),
+        )?;
+        
+        store(&env, &DataKey::Allocations(employer, batch_id), &allocations);
+        
+        Ok(batch_id)
+    }
+    
+    /// The employer's authorized agent locks from the allowance the employer granted this
+    /// contract on the token, without the employer signing each lock (recurring automated payroll)
+    /// Shorthand for `lock_payroll` with `operator` and default options; the allowance bounds
+    /// what can be locked and the agent picks the payout date
+    pub fn lock_payroll_from_allowance(
+        env: Env,
+        operator: Address,
+        employer: Address,
+        total_amount: i128,
+        payout_date: u64,
+    ) -> Result<u64, Error> {
+        Self::lock_payroll(env, employer, total_amount, payout_date, 0, Some(operator), LockOptions::default())
+    }
+    
+    /// Lock funds with the SDP wallet the batch will be released to, so that once due anyone
+    /// can release it with `poke` (set-and-forget payroll)
+    pub fn lock_payroll_with_sdp(
+        env: Env,
+        employer: Address,
+        total_amount: i128,
+        payout_date: u64,
+        sdp_wallet_address: Address,
+    ) -> Result<u64, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        let batch_id = lock_funds(
+            &env,
+            &employer,
+            total_amount,
+            payout_date,
+            0,
+            Funding::Transfer,
+            &LockOptions::default(),
+        )?;
+        
+        store(&env, &DataKey::BatchSdpWallet(employer, batch_id), &sdp_wallet_address);
+        
+        Ok(batch_id)
+    }
+    
+    /// Lock several vault assets (e.g. USDC and EURC) into a multi-asset DeFindex vault
+    /// `tokens` must list each of the vault's assets once, in the vault's order, starting with
+    /// the configured token, which principal, TVL and yield are accounted in; `amounts` pairs
+    /// up with `tokens`
+    /// Reverts with `InsufficientShares` if the vault mints no shares or fewer than `min_shares`
+    pub fn lock_payroll_multi(
+        env: Env,
+        employer: Address,
+        amounts: Vec<i128>,
+        tokens: Vec<Address>,
+        payout_date: u64,
+        min_shares: i128,
+    ) -> Result<u64, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        if amounts.len() != tokens.len() {
+            return Err(Error::InvalidAssets);
+        }
+        
+        // The vault takes one amount per asset in its own order, so the tokens must match it
+        let defindex_vault: Address = env.storage()
+            .instance()
+            .get(&DataKey::DefindexPoolAddress)
+            .ok_or(Error::NotInitialized)?;
+        let vault_assets = DefindexVaultClient::new(&env, &defindex_vault).get_assets();
+        if tokens.len() != vault_assets.len() {
+            return Err(Error::InvalidAssets);
+        }
+        for (i, token) in tokens.iter().enumerate() {
+            let duplicate = tokens.iter().take(i).any(|other| other == token);
+            if duplicate || vault_assets.get_unchecked(i as u32).address != token {
+                return Err(Error::InvalidAssets);
+            }
+        }
+        
+        let mut assets = Vec::new(&env);
+        for (token, amount) in tokens.iter().zip(amounts.iter()) {
+            assets.push_back((token, amount));
+        }
+        
+        lock_assets(&env, &employer, assets, payout_date, min_shares, Funding::Transfer, &LockOptions::default())
+    }
+    
+    /// Employer deposits tokens into the contract without locking them yet, to be locked later
+    /// (e.g. later in the same transaction) with `lock_existing_balance`
+    /// The deposit is credited to `employer` only; returns the employer's new deposit balance
+    pub fn deposit_funds(env: Env, employer: Address, amount: i128) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        
+        let token: Address = env.storage()
+            .instance()
+            .get(&DataKey::TokenAddress)
+            .ok_or(Error::NotInitialized)?;
+        let token_client = TokenClient::new(&env, &token);
+        
+        // Credit what actually arrived; fee-on-transfer tokens deliver less than `amount`
+        let balance_before = token_client.balance(&env.current_contract_address());
+        token_client.transfer(&employer, env.current_contract_address(), &amount);
+        let received = token_client
+            .balance(&env.current_contract_address())
+            .checked_sub(balance_before)
+            .ok_or(Error::InvalidAmount)?;
+        
+        let credit = adjust_deposit(&env, &employer, received)?;
+        env.events().publish((symbol_short!("deposited"), employer), received);
+        
+        Ok(credit)
+    }
+    
+    /// Employer takes back deposited tokens it has not locked; returns the remaining deposit balance
+    pub fn withdraw_deposit(env: Env, employer: Address, amount: i128) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        
+        let token: Address = env.storage()
+            .instance()
+            .get(&DataKey::TokenAddress)
+            .ok_or(Error::NotInitialized)?;
+        let credit = adjust_deposit(&env, &employer, -amount)?;
+        TokenClient::new(&env, &token).transfer(&env.current_contract_address(), &employer, &amount);
+        
+        Ok(credit)
+    }
+    
+    /// Get an employer's deposited but not yet locked balance (0 if none)
+    pub fn get_deposit(env: Env, employer: Address) -> i128 {
+        bump_instance(&env);
+        
+        load(&env, &DataKey::Deposit(employer))
+            .unwrap_or(0)
+    }
+    
+    /// Employer locks tokens it deposited earlier with `deposit_funds` instead of transferring
+    /// them in; fails with `InsufficientFunds` unless the employer's own deposit covers `amount`
+    pub fn lock_existing_balance(
+        env: Env,
+        employer: Address,
+        amount: i128,
+        payout_date: u64,
+    ) -> Result<u64, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        check_nonnegative_amount(amount)?;
+        
+        adjust_deposit(&env, &employer, -amount)?;
+        
+        lock_funds(&env, &employer, amount, payout_date, 0, Funding::Held, &LockOptions::default())
+    }
+    
+    /// Employer locks the same `amount` for each of `occurrences` upcoming payrolls, one
+    /// batch (and vault deposit) per payroll, paying out every `period_secs` from now
+    /// At most 12 occurrences; returns the batch_ids in payout order
+    pub fn lock_payroll_recurring(
+        env: Env,
+        employer: Address,
+        amount: i128,
+        period_secs: u64,
+        occurrences: u32,
+    ) -> Result<Vec<u64>, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        if occurrences == 0 {
+            return Err(Error::InvalidAmount);
+        }
+        if occurrences > MAX_RECURRING_LOCKS {
+            return Err(Error::TooManyBatches);
+        }
+        if period_secs == 0 {
+            return Err(Error::InvalidPayoutDate);
+        }
+        
+        let now = env.ledger().timestamp();
+        let mut batch_ids = Vec::new(&env);
+        for i in 1..=occurrences as u64 {
+            let payout_date = period_secs
+                .checked_mul(i)
+                .and_then(|offset| now.checked_add(offset))
+                .ok_or(Error::InvalidPayoutDate)?;
+            batch_ids.push_back(lock_funds(
+                &env,
+                &employer,
+                amount,
+                payout_date,
+                0,
+                Funding::Transfer,
+                &LockOptions::default(),
+            )?);
+        }
+        
+        Ok(batch_ids)
+    }
+    
+    /// Release principal to SDP (Stellar Disbursement Platform) for employee distribution
+    /// Withdraws funds from DeFindex vault and transfers principal to the SDP wallet, or back
+    /// to the employer for self-distributed payroll
+    /// Only allowlisted operators may trigger a release
+    pub fn release_to_sdp(
+        env: Env,
+        operator: Address,
+        employer: Address,
+        batch_id: u64,
+        destination: ReleaseDestination,
+    ) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        require_operator(&env, &operator)?;
+        
+        release_to_wallet(&env, &employer, batch_id, &destination)
+    }
+    
+    /// Employer starts redeeming a batch's shares from a vault whose strategies need a
+    /// redemption request ahead of withdrawal. Returns when `complete_withdrawal` can run;
+    /// repeated requests return the original time
+    pub fn request_withdrawal(env: Env, employer: Address, batch_id: u64) -> Result<u64, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        bump_instance(&env);
+        
+        let key = DataKey::PayrollLock(employer.clone(), batch_id);
+        let mut lock: PayrollLock = load(&env, &key)
+            .ok_or(Error::BatchNotFound)?;
+        
+        check_not_cancelled(&lock)?;
+        
+        if lock.funds_released {
+            return Err(Error::AlreadyReleased);
+        }
+        if lock.withdrawal_requested_at > 0 {
+            return Ok(lock.withdrawal_ready_at);
+        }
+        
+        lock.withdrawal_ready_at = DefindexVaultClient::new(&env, &lock.vault)
+            .request_withdraw(&lock.vault_shares, &env.current_contract_address());
+        lock.withdrawal_requested_at = env.ledger().timestamp();
+        save_lock(&env, &key, &lock);
+        
+        record_transition(&env, &employer, batch_id, symbol_short!("requested"));
+        env.events().publish((symbol_short!("requested"), batch_id), (employer, lock.withdrawal_ready_at));
+        Ok(lock.withdrawal_ready_at)
+    }
+    
+    /// Employer finishes a redemption started with `request_withdrawal` and releases the
+    /// batch to `sdp_wallet_address`; fails with `WithdrawalNotReady` before the vault's delay
+    pub fn complete_withdrawal(
+        env: Env,
+        employer: Address,
+        batch_id: u64,
+        sdp_wallet_address: Address,
+    ) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        let lock = Self::get_status(env.clone(), employer.clone(), batch_id)?;
+        if lock.withdrawal_requested_at == 0 {
+            return Err(Error::WithdrawalNotReady);
+        }
+        
+        release_to_wallet(&env, &employer, batch_id, &ReleaseDestination::Sdp(sdp_wallet_address))
+    }
+    
+    /// Permissionless release of a due batch locked with `lock_payroll_with_sdp` to the SDP
+    /// wallet fixed at lock time; same path and keeper reward as `release_by_keeper`
+    /// Returns the reward paid
+    pub fn poke(env: Env, keeper: Address, employer: Address, batch_id: u64) -> Result<i128, Error> {
+        Self::release_by_keeper(env, keeper, employer, batch_id)
+    }
+    
+    /// Permissionless release of a due batch to the SDP wallet fixed for it at lock time
+    /// (`lock_payroll_with_sdp`), else to the employer's registered SDP wallet
+    /// The keeper is paid `keeper_reward_bps` of the yield, reducing what the employer
+    /// can claim. Returns the reward paid
+    pub fn release_by_keeper(
+        env: Env,
+        keeper: Address,
+        employer: Address,
+        batch_id: u64,
+    ) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        keeper.require_auth();
+        
+        // Keepers can only send principal where the employer told us to
+        let sdp_wallet_address: Address = load(&env, &DataKey::BatchSdpWallet(employer.clone(), batch_id))
+            .or_else(|| load(&env, &DataKey::SdpWallet(employer.clone())))
+            .ok_or(Error::Unauthorized)?;
+        
+        let yield_earned = release_to_wallet(&env, &employer, batch_id, &ReleaseDestination::Sdp(sdp_wallet_address))?;
+        
+        let reward_bps = settings(&env).keeper_reward_bps.unwrap_or(0);
+        let mut reward = yield_earned
+            .checked_mul(reward_bps as i128)
+            .and_then(|v| v.checked_div(BPS_DENOMINATOR))
+            .ok_or(Error::InvalidAmount)?;
+        
+        if reward > 0 {
+            let key = DataKey::PayrollLock(employer, batch_id);
+            let mut lock: PayrollLock = load(&env, &key)
+                .ok_or(Error::BatchNotFound)?;
+            redeem_yield_shares(&env, &key, &mut lock);
+            reward = reward.min(lock.yield_earned);
+            lock.yield_earned -= reward;
+            save_lock(&env, &key, &lock);
+            
+            TokenClient::new(&env, &lock_token(&lock)).transfer(&env.current_contract_address(), &keeper, &reward);
+        }
+        
+        env.events().publish((symbol_short!("keeper"), batch_id), (keeper, reward));
+        Ok(reward)
+    }
+    
+    /// Release every due, unreleased batch among up to `limit` (at most 50) of an employer's
+    /// batches from position `start` (sequential ids, then salted ones) to one SDP wallet
+    /// The employer or an allowlisted operator may trigger it
+    /// Returns (batch_id, yield earned) per released batch; batches that can't be released now
+    /// (not yet due, no shares, redemption pending) are skipped
+    pub fn release_all_due(
+        env: Env,
+        caller: Address,
+        employer: Address,
+        sdp_wallet_address: Address,
+        start: u32,
+        limit: u32,
+    ) -> Result<Vec<(u64, i128)>, Error> {
+        require_not_frozen(&env)?;
+        
+        if caller == employer {
+            employer.require_auth();
+        } else {
+            require_operator(&env, &caller)?;
+        }
+        bump_instance(&env);
+        
+        let destination = ReleaseDestination::Sdp(sdp_wallet_address);
+        let mut yields = Vec::new(&env);
+        for batch_id in batch_id_page(&env, &employer, start, limit)?.iter() {
+            // Skip batches that would fail before reaching the vault; a failure after the
+            // withdrawal still reverts the whole call, as that batch's shares are spent
+            if prepare_release(&env, &employer, batch_id).is_ok() {
+                let yield_earned = release_to_wallet(&env, &employer, batch_id, &destination)?;
+                yields.push_back((batch_id, yield_earned));
+            }
+        }
+        
+        Ok(yields)
+    }
+    
+    /// Release principal split across several SDP wallets (e.g. regional SDP instances)
+    /// Only allowlisted operators may trigger a release
+    /// The split amounts must add up exactly to the batch's locked principal
+    pub fn release_to_multiple_sdp(
+        env: Env,
+        operator: Address,
+        employer: Address,
+        batch_id: u64,
+        splits: Vec<(Address, i128)>,
+    ) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        require_operator(&env, &operator)?;
+        
+        // Splits are amounts of the configured token, so they can't cover other assets
+        let status = Self::get_status(env.clone(), employer.clone(), batch_id)?;
+        if status.assets.len() > 1 {
+            return Err(Error::InvalidAssets);
+        }
+        
+        // Validate splits against the locked principal before touching the vault
+        let total_amount = status.total_amount;
+        let mut split_total: i128 = 0;
+        for (_, amount) in splits.iter() {
+            check_nonnegative_amount(amount)?;
+            split_total = split_total
+                .checked_add(amount)
+                .ok_or(Error::InvalidAmount)?;
+        }
+        if splits.is_empty() || split_total != total_amount {
+            return Err(Error::InvalidAmount);
+        }
+        
+        let (lock, principal_out) = withdraw_lock(&env, &employer, batch_id)?;
+        
+        // Splits assume the full principal came back from the vault
+        let (_, principal_out) = principal_out.get_unchecked(0);
+        if principal_out < total_amount {
+            return Err(Error::InsufficientFunds);
+        }
+        
+        // Get stored token address
+        let token: Address = env.storage()
+            .instance()
+            .get(&DataKey::TokenAddress)
+            .ok_or(Error::NotInitialized)?;
+        
+        // Transfer each split to its SDP wallet
+        let token_client = TokenClient::new(&env, &token);
+        for (wallet, amount) in splits.iter() {
+            if amount > 0 {
+                token_client.transfer(&env.current_contract_address(), &wallet, &amount);
+            }
+            env.events().publish(
+                (symbol_short!("sdp_ready"), wallet),
+                (batch_id, amount, employer.clone()),
+            );
+        }
+        
+        let yield_earned = unclaimed_yield(&env, &lock);
+        env.events().publish(
+            (symbol_short!("released"), batch_id, yield_earned),
+            splits
+        );
+        Ok(yield_earned)
+    }
+    
+    /// Employer claims yield earned during lock period
+    pub fn claim_yield(
+        env: Env,
+        employer: Address,
+        batch_id: u64,
+    ) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        claim_lock_yield(&env, &employer, batch_id, None, true)
+    }
+    
+    /// Employer claims part of a batch's yield, leaving the rest for later
+    /// `amount` comes out of the remaining yield and the platform fee is taken from it
+    pub fn claim_yield_partial(
+        env: Env,
+        employer: Address,
+        batch_id: u64,
+        amount: i128,
+    ) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        
+        claim_lock_yield(&env, &employer, batch_id, Some(amount), true)
+    }
+    
+    /// Employer releases a due batch to an SDP wallet and claims its yield atomically
+    /// Either both steps succeed or the whole call reverts
+    pub fn release_and_claim(
+        env: Env,
+        employer: Address,
+        batch_id: u64,
+        sdp_wallet_address: Address,
+    ) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        release_to_wallet(&env, &employer, batch_id, &ReleaseDestination::Sdp(sdp_wallet_address))?;
+        claim_lock_yield(&env, &employer, batch_id, None, true)
+    }
+    
+    /// Employer sends the employees' share of a batch's yield, set aside by its claims, to the
+    /// SDP wallet for distribution; only once the yield is fully claimed, and only once
+    /// Returns the amount sent
+    pub fn distribute_employee_yield(
+        env: Env,
+        employer: Address,
+        batch_id: u64,
+        sdp_wallet: Address,
+    ) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        bump_instance(&env);
+        
+        let key = DataKey::PayrollLock(employer.clone(), batch_id);
+        let mut lock: PayrollLock = load(&env, &key)
+            .ok_or(Error::BatchNotFound)?;
+        
+        check_not_cancelled(&lock)?;
+        
+        if !lock.funds_released {
+            return Err(Error::NotYetReleased);
+        }
+        if lock.status != LockStatus::Claimed {
+            return Err(Error::InvalidStateTransition);
+        }
+        if lock.employee_yield_distributed {
+            return Err(Error::AlreadyClaimed);
+        }
+        
+        // Record the distribution before the transfer
+        lock.employee_yield_distributed = true;
+        save_lock(&env, &key, &lock);
+        
+        if lock.employee_yield > 0 {
+            TokenClient::new(&env, &lock_token(&lock)).transfer(
+                &env.current_contract_address(),
+                &sdp_wallet,
+                &lock.employee_yield,
+            );
+        }
+        
+        record_transition(&env, &employer, batch_id, symbol_short!("emp_yield"));
+        env.events().publish((symbol_short!("emp_yield"), batch_id), (sdp_wallet, lock.employee_yield));
+        Ok(lock.employee_yield)
+    }
+    
+    /// Roll a released batch's claimable yield straight into a new lock
+    /// The employer's share (after platform fee) never leaves the contract; returns the new batch_id
+    pub fn relock_yield(
+        env: Env,
+        employer: Address,
+        batch_id: u64,
+        new_payout_date: u64,
+    ) -> Result<u64, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        let employer_share = claim_lock_yield(&env, &employer, batch_id, None, false)?;
+        if employer_share <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        
+        lock_funds(
+            &env,
+            &employer,
+            employer_share,
+            new_payout_date,
+            0,
+            Funding::Held,
+            &LockOptions::default(),
+        )
+    }
+    
+    /// Employer cancels an unreleased lock and gets back everything the vault returns, less
+    /// the platform fee on any yield. Only allowed within `cancel_window_secs` of locking; returns the configured-token amount refunded
+    pub fn cancel_lock(env: Env, employer: Address, batch_id: u64) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        
+        // Extend storage TTL
+        bump_instance(&env);
+        
+        let lock: PayrollLock = load(&env, &DataKey::PayrollLock(employer.clone(), batch_id))
+            .ok_or(Error::BatchNotFound)?;
+        
+        check_not_cancelled(&lock)?;
+        
+        if lock.funds_released {
+            return Err(Error::AlreadyReleased);
+        }
+        
+        // Once the window closes the funds are committed to payroll
+        let cancel_window = settings(&env).cancel_window_secs.unwrap_or(u64::MAX);
+        if env.ledger().timestamp() > lock.lock_date.saturating_add(cancel_window) {
+            return Err(Error::CancelWindowClosed);
+        }
+        
+        let refunded = cancel_batch(&env, &employer, batch_id, lock, &employer)?;
+        env.events().publish((symbol_short!("cancel"), batch_id), (employer, refunded));
+        Ok(refunded)
+    }
+    
+    /// Admins recover an unreleased lock whose employer can no longer act (e.g. lost keys),
+    /// refunding everything the vault returns to `refund_to` after a dispute process
+    /// Only callable 90 days or more past the payout date, otherwise `TimelockActive`
+    /// Privileged: returns the amount refunded once the admin threshold is met, None until then
+    pub fn admin_cancel_lock(
+        env: Env,
+        approver: Address,
+        employer: Address,
+        batch_id: u64,
+        refund_to: Address,
+    ) -> Result<Option<i128>, Error> {
+        require_not_frozen(&env)?;
+        
+        bump_instance(&env);
+        
+        let operation = (symbol_short!("admcancel"), employer.clone(), batch_id, refund_to.clone()).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(None);
+        }
+        
+        let lock: PayrollLock = load(&env, &DataKey::PayrollLock(employer.clone(), batch_id))
+            .ok_or(Error::BatchNotFound)?;
+        
+        check_not_cancelled(&lock)?;
+        
+        if lock.funds_released {
+            return Err(Error::AlreadyReleased);
+        }
+        
+        // The delay gives keepers and the employer ample time to release normally first
+        if env.ledger().timestamp() < lock.payout_date.saturating_add(ADMIN_CANCEL_DELAY_SECS) {
+            return Err(Error::TimelockActive);
+        }
+        
+        let refunded = cancel_batch(&env, &employer, batch_id, lock, &refund_to)?;
+        env.events().publish((symbol_short!("recovered"), batch_id), (employer, refund_to, refunded));
+        Ok(Some(refunded))
+    }
+    
+    /// Employer redeems part of an unreleased batch's vault shares for emergency liquidity,
+    /// within the cancel window. Only shares worth more than the batch's principal can be
+    /// redeemed (`InsufficientFunds` otherwise), so the payout stays covered. With `force`
+    /// the principal shrinks in proportion to the shares redeemed instead; the window still applies.
+    /// At least one share must stay locked. The yield part of the proceeds pays the platform
+    /// fee; returns the configured-token amount sent to the employer
+    pub fn emergency_partial_withdraw(
+        env: Env,
+        employer: Address,
+        batch_id: u64,
+        shares_to_redeem: i128,
+        force: bool,
+    ) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        employer.require_auth();
+        bump_instance(&env);
+        
+        let key = DataKey::PayrollLock(employer.clone(), batch_id);
+        let mut lock: PayrollLock = load(&env, &key)
+            .ok_or(Error::BatchNotFound)?;
+        
+        check_not_cancelled(&lock)?;
+        
+        if lock.funds_released {
+            return Err(Error::AlreadyReleased);
+        }
+        if shares_to_redeem <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        // A batch left with no shares could never be released
+        if shares_to_redeem >= lock.vault_shares {
+            return Err(Error::InsufficientShares);
+        }
+        
+        // Same window as cancel_lock, forced or not: afterwards the funds are committed to payroll
+        let cancel_window = settings(&env).cancel_window_secs.unwrap_or(u64::MAX);
+        if env.ledger().timestamp() > lock.lock_date.saturating_add(cancel_window) {
+            return Err(Error::CancelWindowClosed);
+        }
+        
+        let remaining_shares = lock.vault_shares - shares_to_redeem;
+        let mut remaining_assets = Vec::new(&env);
+        if force {
+            // Principal given up by each asset, pro rata to the shares redeemed
+            for (asset, amount) in lock.assets.iter() {
+                let redeemed = amount
+                    .checked_mul(shares_to_redeem)
+                    .map(|v| v / lock.vault_shares)
+                    .ok_or(Error::ArithmeticOverflow)?;
+                remaining_assets.push_back((asset, amount - redeemed));
+            }
+        } else {
+            // The shares left must still be worth the payout principal
+            let remaining_value = DefindexVaultClient::new(&env, &lock.vault)
+                .get_asset_amounts_per_shares(&remaining_shares)
+                .get(0)
+                .unwrap_or(0);
+            if remaining_value < lock.total_amount {
+                return Err(Error::InsufficientFunds);
+            }
+            remaining_assets = lock.assets.clone();
+        }
+        let (_, remaining_principal) = remaining_assets.get_unchecked(0);
+        let principal_redeemed = lock.total_amount - remaining_principal;
+        
+        // Shrink the batch before calling out to the vault
+        decrease_tvl(&env, principal_redeemed);
+        lock.total_amount = remaining_principal;
+        lock.assets = remaining_assets;
+        lock.vault_shares = remaining_shares;
+        save_lock(&env, &key, &lock);
+        update_summary(&env, &employer, |summary| summary.amount_locked -= principal_redeemed);
+        
+        let mut min_amounts_out = Vec::new(&env);
+        for _ in lock.assets.iter() {
+            min_amounts_out.push_back(0);
+        }
+        let withdrawn_amounts = DefindexVaultClient::new(&env, &lock.vault).withdraw(
+            &shares_to_redeem,
+            &min_amounts_out,
+            &env.current_contract_address(),
+        );
+        
+        // Whatever came back beyond the principal given up is yield, which pays the platform fee
+        let withdrawn = withdrawn_amounts.get(0).unwrap_or(0);
+        let fee = collect_platform_fee(&env, &lock, batch_id, withdrawn - principal_redeemed)?;
+        
+        let mut paid: i128 = 0;
+        for (i, (asset, _)) in lock.assets.iter().enumerate() {
+            let mut amount = withdrawn_amounts.get(i as u32).unwrap_or(0);
+            if i == 0 {
+                amount -= fee;
+                paid = amount;
+            }
+            if amount > 0 {
+                TokenClient::new(&env, &asset).transfer(&env.current_contract_address(), &employer, &amount);
+            }
+        }
+        
+        record_transition(&env, &employer, batch_id, symbol_short!("partial"));
+        env.events().publish((symbol_short!("partial"), batch_id), (employer, shares_to_redeem, paid));
+        Ok(paid)
+    }
+    
+    /// Move an unreleased lock to a new employer address (e.g. after a restructuring)
+    /// The lock is re-keyed under the new employer's next batch_id, which is returned
+    /// The recipient must be allowed to lock and stay under the per-employer cap
+    pub fn transfer_lock_ownership(
+        env: Env,
+        current_employer: Address,
+        batch_id: u64,
+        new_employer: Address,
+    ) -> Result<u64, Error> {
+        require_not_frozen(&env)?;
+        
+        current_employer.require_auth();
+        
+        // Extend storage TTL
+        bump_instance(&env);
+        
+        let old_key = DataKey::PayrollLock(current_employer.clone(), batch_id);
+        let mut lock: PayrollLock = load(&env, &old_key)
+            .ok_or(Error::BatchNotFound)?;
+        
+        check_not_cancelled(&lock)?;
+        
+        // Only live locks can change hands
+        if lock.funds_released {
+            return Err(Error::AlreadyReleased);
+        }
+        
+        // The recipient takes on the lock as if they had created it
+        if has_record(&env, &DataKey::Blocked(new_employer.clone())) {
+            return Err(Error::Unauthorized);
+        }
+        let max_per_employer = settings(&env).max_locked_per_employer.unwrap_or(i128::MAX);
+        if new_employer != current_employer
+            && employer_locked(&env, &new_employer).saturating_add(lock.total_amount) > max_per_employer
+        {
+            return Err(Error::CapExceeded);
+        }
+        
+        // Allocate a fresh batch_id for the new employer so keys never collide
+        let new_batch_id: u64 = load(&env, &DataKey::NextBatchId(new_employer.clone()))
+            .unwrap_or(0);
+        
+        lock.employer = new_employer.clone();
+        save_lock(&env, &DataKey::PayrollLock(new_employer.clone(), new_batch_id), &lock);
+        store(&env, &DataKey::NextBatchId(new_employer.clone()), &(new_batch_id + 1));
+        if new_batch_id == 0 {
+            register_employer(&env, &new_employer);
+        }
+        remove_lock(&env, &old_key);
+        
+        // The summaries follow the principal so each employer's totals match what they hold
+        let amount = lock.total_amount;
+        update_summary(&env, &current_employer, |summary| {
+            summary.total_locks = summary.total_locks.saturating_sub(1);
+            summary.amount_locked -= amount;
+        });
+        update_summary(&env, &new_employer, |summary| {
+            summary.total_locks += 1;
+            summary.amount_locked += amount;
+        });
+        
+        // Carry any per-employee allocations over with the lock
+        let old_allocations_key = DataKey::Allocations(current_employer.clone(), batch_id);
+        let allocations: Option<Vec<(Address, i128)>> = load(&env, &old_allocations_key);
+        if let Some(allocations) = allocations {
+            store(&env, &DataKey::Allocations(new_employer.clone(), new_batch_id), &allocations);
+            drop_record(&env, &old_allocations_key);
+        }
+        
+        // So does an SDP wallet fixed at lock time
+        let old_wallet_key = DataKey::BatchSdpWallet(current_employer.clone(), batch_id);
+        let wallet: Option<Address> = load(&env, &old_wallet_key);
+        if let Some(wallet) = wallet {
+            store(&env, &DataKey::BatchSdpWallet(new_employer.clone(), new_batch_id), &wallet);
+            drop_record(&env, &old_wallet_key);
+        }
+        
+        // The history follows the lock too
+        let old_history_key = DataKey::History(current_employer.clone(), batch_id);
+        let history: Option<Vec<Transition>> = load(&env, &old_history_key);
+        if let Some(history) = history {
+            store(&env, &DataKey::History(new_employer.clone(), new_batch_id), &history);
+            drop_record(&env, &old_history_key);
         }
         
-        env.storage()
-            .instance()
-            .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
-        
-        env.storage().instance().set(&DataKey::DefindexPoolAddress, &defindex_pool);
-        env.storage().instance().set(&DataKey::TokenAddress, &token);
+        // A retry of the original lock must not resolve to a batch the employer no longer owns
+        let old_idem_key = DataKey::BatchIdemKey(current_employer.clone(), batch_id);
+        let idem_key: Option<BytesN<32>> = load(&env, &old_idem_key);
+        if let Some(idem_key) = idem_key {
+            drop_record(&env, &DataKey::IdemKey(current_employer.clone(), idem_key));
+            drop_record(&env, &old_idem_key);
+        }
+        record_transition(&env, &new_employer, new_batch_id, symbol_short!("moved"));
         
-        Ok(())
+        env.events().publish(
+            (symbol_short!("transfer"), batch_id, new_batch_id),
+            (current_employer, new_employer)
+        );
+        Ok(new_batch_id)
     }
     
-    /// Employer locks funds for payroll (before sending to defindex)
-    pub fn lock_payroll(
-        env: Env,
-        employer: Address,
-        total_amount: i128,
-        payout_date: u64,
-    ) -> Result<u64, Error> {
-        employer.require_auth();
-        
-        // Validate amount
-        check_nonnegative_amount(total_amount)?;
-        
-        // Extend storage TTL
-        env.storage()
-            .instance()
-            .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+    /// Estimate how many vault shares depositing `amount` would mint right now
+    /// Uses the vault's current totals; an empty vault mints shares 1:1
+    pub fn estimate_shares(env: Env, amount: i128) -> Result<i128, Error> {
+        check_nonnegative_amount(amount)?;
+        bump_instance(&env);
         
-        // Verify payout date is in the future
-        if payout_date <= env.ledger().timestamp() {
-            return Err(Error::InvalidPayoutDate);
+        let (total_assets, total_shares) = vault_totals(&env)?;
+        if total_shares == 0 || total_assets == 0 {
+            return Ok(amount);
         }
         
+        amount
+            .checked_mul(total_shares)
+            .and_then(|v| v.checked_div(total_assets))
+            .ok_or(Error::InvalidAmount)
+    }
+    
+    /// Decimals of the configured token, so front-ends can format raw amounts
+    /// Cached per token address, so a different token is looked up afresh
+    pub fn get_token_decimals(env: Env) -> Result<u32, Error> {
+        bump_instance(&env);
+        
         // Get stored token address
         let token: Address = env.storage()
             .instance()
             .get(&DataKey::TokenAddress)
             .ok_or(Error::NotInitialized)?;
         
-        // Get next batch_id for this employer
-        let batch_id: u64 = env.storage()
-            .instance()
-            .get(&DataKey::NextBatchId(employer.clone()))
-            .unwrap_or(0);
+        let cached: Option<(Address, u32)> = env.storage().instance().get(&DataKey::TokenDecimals);
+        if let Some((cached_token, decimals)) = cached {
+            if cached_token == token {
+                return Ok(decimals);
+            }
+        }
         
-        // Transfer tokens from employer to contract
-        let token_client = TokenClient::new(&env, &token);
-        token_client.transfer(
-            &employer,
-            &env.current_contract_address(),
-            &total_amount,
-        );
+        let decimals = TokenClient::new(&env, &token).decimals();
+        env.storage().instance().set(&DataKey::TokenDecimals, &(token, decimals));
+        Ok(decimals)
+    }
+    
+    /// Vault assets per share scaled by 1e7, for off-chain NAV calculation
+    /// Returns 0 while the vault has no shares
+    pub fn get_share_price(env: Env) -> Result<i128, Error> {
+        bump_instance(&env);
         
-        // Get DeFindex vault address
-        let defindex_vault: Address = env.storage()
-            .instance()
-            .get(&DataKey::DefindexPoolAddress)
-            .ok_or(Error::NotInitialized)?;
+        let (total_assets, total_shares) = vault_totals(&env)?;
+        if total_shares == 0 {
+            return Ok(0);
+        }
         
-        let defindex_client = DefindexVaultClient::new(&env, &defindex_vault);
-        let mut amounts_vec = Vec::new(&env);
-        amounts_vec.push_back(total_amount);
-        let mut min_amounts = Vec::new(&env);
-        min_amounts.push_back(total_amount);
+        total_assets
+            .checked_mul(SHARE_PRICE_SCALE)
+            .and_then(|v| v.checked_div(total_shares))
+            .ok_or(Error::InvalidAmount)
+    }
+    
+    /// Convert vault shares to their current underlying amount at the vault's exchange rate
+    /// Returns 0 while the vault has no shares
+    pub fn shares_to_assets(env: Env, shares: i128) -> Result<i128, Error> {
+        check_nonnegative_amount(shares)?;
+        bump_instance(&env);
         
-        // Authorize the token transfer that DeFindex will make
-        env.authorize_as_current_contract(vec![
-            &env,
-            InvokerContractAuthEntry::Contract(SubContractInvocation {
-                context: ContractContext {
-                    contract: token.clone(),
-                    fn_name: Symbol::new(&env, "transfer"),
-                    args: (
-                        env.current_contract_address(),
-                        defindex_vault.clone(),
-                        total_amount,
-                    ).into_val(&env),
-                },
-                sub_invocations: vec![&env],
-            }),
-        ]);
-        
-        // Now call deposit - the authorization above allows DeFindex to transfer our tokens
-        let (_, vault_shares, _) = defindex_client.deposit(
-            &amounts_vec,
-            &min_amounts,
-            &env.current_contract_address(),
-            &true,
-        );
+        let (total_assets, total_shares) = vault_totals(&env)?;
+        if total_shares == 0 {
+            return Ok(0);
+        }
         
-        let lock = PayrollLock {
-            employer: employer.clone(),
-            total_amount,
-            vault_shares,
-            lock_date: env.ledger().timestamp(),
-            payout_date,
-            yield_earned: 0,
-            funds_released: false,
-            yield_claimed: false,
-        };
+        shares
+            .checked_mul(total_assets)
+            .map(|v| v / total_shares)
+            .ok_or(Error::ArithmeticOverflow)
+    }
+    
+    /// Admins sweep yield left unclaimed past the claim window to the fee collector
+    /// Privileged: returns the amount swept once the admin threshold is met, None until then
+    pub fn sweep_unclaimed_yield(
+        env: Env,
+        approver: Address,
+        employer: Address,
+        batch_id: u64,
+    ) -> Result<Option<i128>, Error> {
+        require_not_frozen(&env)?;
+        
+        bump_instance(&env);
         
-        env.storage().instance().set(&DataKey::PayrollLock(employer.clone(), batch_id), &lock);
+        let operation = (symbol_short!("sweep_yld"), employer.clone(), batch_id).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(None);
+        }
         
-        // Increment batch_id for next lock
-        env.storage().instance().set(&DataKey::NextBatchId(employer.clone()), &(batch_id + 1));
+        let mut lock: PayrollLock = load(&env, &DataKey::PayrollLock(employer.clone(), batch_id))
+            .ok_or(Error::BatchNotFound)?;
         
-        env.events().publish((symbol_short!("locked"), batch_id), employer);
+        check_not_cancelled(&lock)?;
         
-        Ok(batch_id)
+        if !lock.funds_released {
+            return Err(Error::NotYetReleased);
+        }
+        if lock.yield_claimed {
+            return Err(Error::AlreadyClaimed);
+        }
+        
+        // Verify the claim window has elapsed (no window configured means never)
+        let claim_window = settings(&env)
+            .claim_window_secs
+            .ok_or(Error::ClaimWindowActive)?;
+        if env.ledger().timestamp() < lock.release_date.saturating_add(claim_window) {
+            return Err(Error::ClaimWindowActive);
+        }
+        
+        // Swept in the batch's own token, to the stored fee collector
+        let token = lock_token(&lock);
+        let fee_collector: Address = env.storage()
+            .instance()
+            .get(&DataKey::FeeCollector)
+            .ok_or(Error::NotInitialized)?;
+        
+        // Collect any yield still invested, then mark it claimed before transferring it out
+        let key = DataKey::PayrollLock(employer.clone(), batch_id);
+        redeem_yield_shares(&env, &key, &mut lock);
+        let swept = lock.yield_earned;
+        set_status(&mut lock, LockStatus::Claimed)?;
+        save_lock(&env, &key, &lock);
+        
+        if swept > 0 {
+            TokenClient::new(&env, &token).transfer(
+                &env.current_contract_address(),
+                &fee_collector,
+                &swept,
+            );
+        }
+        
+        record_transition(&env, &employer, batch_id, symbol_short!("swept"));
+        
+        // Distinct from the `yield` event so indexers don't count this as an employer claim
+        env.events().publish((symbol_short!("swept"), batch_id), (swept, fee_collector));
+        Ok(Some(swept))
     }
     
-    /// Release principal to SDP (Stellar Disbursement Platform) for employee distribution
-    /// Withdraws funds from DeFindex vault and transfers principal to SDP wallet
-    pub fn release_to_sdp(
+    /// Admins move vault shares left on the contract after a batch's release (vault
+    /// rounding dust) to `to`. Shares still backing unreleased batches are never touched
+    /// Privileged: returns the shares moved once the admin threshold is met, None until then
+    pub fn sweep_residual_shares(
         env: Env,
+        approver: Address,
         employer: Address,
         batch_id: u64,
-        sdp_wallet_address: Address,
-    ) -> Result<i128, Error> {
-        // Extend storage TTL
-        env.storage()
-            .instance()
-            .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        to: Address,
+    ) -> Result<Option<i128>, Error> {
+        require_not_frozen(&env)?;
         
-        let mut lock: PayrollLock = env.storage().instance()
-            .get(&DataKey::PayrollLock(employer.clone(), batch_id))
-            .ok_or(Error::NotInitialized)?;
+        bump_instance(&env);
         
-        // Verify payout date has been reached
-        if env.ledger().timestamp() < lock.payout_date {
-            return Err(Error::PayoutDateNotReached);
+        let operation = (symbol_short!("sweep_res"), employer.clone(), batch_id, to.clone()).to_xdr(&env);
+        if !approve_privileged(&env, &approver, operation)? {
+            return Ok(None);
         }
         
-        // Verify funds haven't already been released
-        if lock.funds_released {
-            return Err(Error::AlreadyReleased);
+        let lock: PayrollLock = load(&env, &DataKey::PayrollLock(employer, batch_id))
+            .ok_or(Error::BatchNotFound)?;
+        
+        if lock.status == LockStatus::Locked {
+            return Err(Error::NotYetReleased);
         }
         
-        // Get stored token address
-        let token: Address = env.storage()
-            .instance()
-            .get(&DataKey::TokenAddress)
-            .ok_or(Error::NotInitialized)?;
+        // Vault shares are themselves a token; anything beyond what live batches need is residue
+        let share_token = TokenClient::new(&env, &lock.vault);
+        let residual = share_token
+            .balance(&env.current_contract_address())
+            .saturating_sub(outstanding_shares(&env, Some(&lock.vault)))
+            .max(0);
+        if residual > 0 {
+            share_token.transfer(&env.current_contract_address(), &to, &residual);
+        }
         
-        // Get DeFindex vault address
-        let defindex_vault: Address = env.storage()
+        env.events().publish((symbol_short!("residual"), batch_id), (to, residual));
+        Ok(Some(residual))
+    }
+    
+    /// Get the yield `claim_yield` would currently pay the employer (net of platform fee and the employees' share)
+    /// Returns 0 until funds are released and after the yield has been claimed
+    pub fn get_claimable_yield(env: Env, employer: Address, batch_id: u64) -> Result<i128, Error> {
+        let lock = Self::get_status(env.clone(), employer, batch_id)?;
+        
+        if lock.status != LockStatus::Released {
+            return Ok(0);
+        }
+        
+        let unclaimed = unclaimed_yield(&env, &lock);
+        let fee = platform_fee(&env, &lock, unclaimed)?;
+        Ok(unclaimed - fee - employee_share(&env, &lock, unclaimed - fee)?)
+    }
+    
+    /// Get the contract-wide yield split between employer, employees and platform fee
+    pub fn get_split_config(env: Env) -> Result<SplitConfig, Error> {
+        bump_instance(&env);
+        
+        let platform_fee_bps: u32 = env.storage()
             .instance()
-            .get(&DataKey::DefindexPoolAddress)
+            .get(&DataKey::PlatformFeeBps)
             .ok_or(Error::NotInitialized)?;
+        split_config(platform_fee_bps, settings(&env).employee_yield_bps.unwrap_or(0))
+    }
+    
+    /// Get how a batch's yield splits between employer, employees and platform fee
+    /// All portions are 0 until funds are released
+    pub fn get_yield_breakdown(env: Env, employer: Address, batch_id: u64) -> Result<YieldBreakdown, Error> {
+        let lock = Self::get_status(env.clone(), employer, batch_id)?;
         
-        // Withdraw from DeFindex vault
-        let defindex_client = DefindexVaultClient::new(&env, &defindex_vault);
-        let mut min_amounts_out = Vec::new(&env);
-        min_amounts_out.push_back(lock.total_amount);
+        if !lock.funds_released {
+            return Ok(YieldBreakdown::default());
+        }
         
-        let withdrawn_amounts = defindex_client.withdraw(
-            &lock.vault_shares,
-            &min_amounts_out,
-            &env.current_contract_address(),
-        );
+        let unclaimed = unclaimed_yield(&env, &lock);
+        let fee = platform_fee(&env, &lock, unclaimed)?;
+        let employees = employee_share(&env, &lock, unclaimed - fee)?;
+        Ok(YieldBreakdown {
+            employer_share: unclaimed - fee - employees,
+            employee_share: employees,
+            platform_fee: fee,
+        })
+    }
+    
+    /// Get a batch's unclaimed yield in whole units of 10^scale (e.g. scale 7 turns stroops
+    /// into XLM), truncated; `get_yield_remainder` gives the part left over
+    /// `scale` may not exceed the token's decimals
+    pub fn get_yield_scaled(env: Env, employer: Address, batch_id: u64, scale: u32) -> Result<i128, Error> {
+        let divisor = scale_divisor(scale, Self::get_token_decimals(env.clone())?)?;
+        let lock = Self::get_status(env.clone(), employer, batch_id)?;
+        Ok(unclaimed_yield(&env, &lock) / divisor)
+    }
+    
+    /// Get the fractional part `get_yield_scaled` truncates, in raw token units
+    pub fn get_yield_remainder(env: Env, employer: Address, batch_id: u64, scale: u32) -> Result<i128, Error> {
+        let divisor = scale_divisor(scale, Self::get_token_decimals(env.clone())?)?;
+        let lock = Self::get_status(env.clone(), employer, batch_id)?;
+        Ok(unclaimed_yield(&env, &lock) % divisor)
+    }
+    
+    /// Get current payroll lock status
+    pub fn get_status(env: Env, employer: Address, batch_id: u64) -> Result<PayrollLock, Error> {
+        bump_instance(&env);
         
-        // Calculate actual yield earned
-        let total_withdrawn = withdrawn_amounts.get(0).unwrap_or(0);
-        let yield_earned = total_withdrawn
-            .checked_sub(lock.total_amount)
-            .unwrap_or(0);
+        load(&env, &DataKey::PayrollLock(employer, batch_id))
+            .ok_or(Error::BatchNotFound)
+    }
+    
+    /// Whether a batch was cancelled, as opposed to never having existed (both lack a live lock)
+    /// Cancelling leaves a compact persistent marker, so the answer doesn't depend on the full record
+    pub fn was_cancelled(env: Env, employer: Address, batch_id: u64) -> bool {
+        bump_instance(&env);
         
-        // Transfer principal to SDP wallet for employee distribution
-        let token_client = TokenClient::new(&env, &token);
-        token_client.transfer(
-            &env.current_contract_address(),
-            &sdp_wallet_address,
-            &lock.total_amount,
-        );
+        has_record(&env, &DataKey::Cancelled(employer, batch_id))
+    }
+    
+    /// Get the status of several batches at once; missing batches come back as None
+    pub fn get_status_batch(env: Env, employer: Address, batch_ids: Vec<u64>) -> Result<Vec<Option<PayrollLock>>, Error> {
+        if batch_ids.len() > MAX_STATUS_BATCH {
+            return Err(Error::TooManyBatches);
+        }
+        bump_instance(&env);
         
-        // Update lock state
-        lock.yield_earned = yield_earned;
-        lock.funds_released = true;
-        env.storage().instance().set(&DataKey::PayrollLock(employer.clone(), batch_id), &lock);
+        let mut locks = Vec::new(&env);
+        for batch_id in batch_ids.iter() {
+            let lock: Option<PayrollLock> = load(&env, &DataKey::PayrollLock(employer.clone(), batch_id));
+            locks.push_back(lock);
+        }
         
-        env.events().publish(
-            (symbol_short!("released"), batch_id, yield_earned), 
-            sdp_wallet_address
-        );
-        Ok(yield_earned)
+        Ok(locks)
     }
     
-    /// Employer claims yield earned during lock period
-    pub fn claim_yield(
+    /// Any admin or co-admin fetches locks across employers: the i-th result is batch
+    /// `batch_ids[i]` of `employers[i]`, None if missing. Up to 50 pairs; the lists must be
+    /// the same length
+    pub fn admin_get_locks(
         env: Env,
-        employer: Address,
-        batch_id: u64,
-    ) -> Result<i128, Error> {
-        employer.require_auth();
+        admin: Address,
+        employers: Vec<Address>,
+        batch_ids: Vec<u64>,
+    ) -> Result<Vec<Option<PayrollLock>>, Error> {
+        require_any_admin(&env, &admin)?;
+        
+        if employers.len() != batch_ids.len() {
+            return Err(Error::InvalidAmount);
+        }
+        if batch_ids.len() > MAX_STATUS_BATCH {
+            return Err(Error::TooManyBatches);
+        }
+        bump_instance(&env);
+        
+        let mut locks = Vec::new(&env);
+        for (employer, batch_id) in employers.iter().zip(batch_ids.iter()) {
+            let lock: Option<PayrollLock> = load(&env, &DataKey::PayrollLock(employer, batch_id));
+            locks.push_back(lock);
+        }
+        
+        Ok(locks)
+    }
+    
+    /// Get an employer's running totals of locks, releases, claims and cancellations in one read
+    /// Moved batches are counted under the employer that now owns them
+    pub fn get_summary(env: Env, employer: Address) -> EmployerSummary {
+        bump_instance(&env);
+        
+        load(&env, &DataKey::Summary(employer))
+            .unwrap_or_default()
+    }
+    
+    /// Any admin or co-admin lists the employers that have ever locked, up to `limit` (at most
+    /// 50) from position `start` in the order they first locked
+    pub fn get_employers(env: Env, admin: Address, start: u32, limit: u32) -> Result<Vec<Address>, Error> {
+        require_any_admin(&env, &admin)?;
+        bump_instance(&env);
+        
+        employer_page(&env, start, limit)
+    }
+    
+    /// Get the configured token balance held by the contract itself rather than in the vault,
+    /// for diagnostics; 0 before initialize
+    pub fn get_contract_balance(env: Env) -> i128 {
+        bump_instance(&env);
+        
+        let token: Option<Address> = env.storage().instance().get(&DataKey::TokenAddress);
+        match token {
+            Some(token) => TokenClient::new(&env, &token).balance(&env.current_contract_address()),
+            None => 0,
+        }
+    }
+    
+    /// Get total principal currently locked across all employers
+    pub fn get_tvl(env: Env) -> i128 {
+        bump_instance(&env);
         
-        // Extend storage TTL
         env.storage()
             .instance()
-            .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+            .get(&DataKey::TotalValueLocked)
+            .unwrap_or(0)
+    }
+    
+    /// Get the DeFindex shares held for batches across all employers and vaults: principal of
+    /// unreleased batches plus yield left invested after release
+    pub fn get_total_shares(env: Env) -> i128 {
+        bump_instance(&env);
+        
+        outstanding_shares(&env, None)
+    }
+    
+    /// Invariant check for monitoring, one page of up to `limit` (at most 50) employers from
+    /// position `start`: their obligations (unreleased principal, unclaimed yield) and the
+    /// current value of the vault shares backing their unreleased batches. The page at 0 also
+    /// counts unlocked deposits and the contract's token balance. Summed over every page,
+    /// holdings below obligations signal accounting drift
+    pub fn check_solvency(env: Env, start: u32, limit: u32) -> Result<SolvencyPage, Error> {
+        bump_instance(&env);
         
-        let mut lock: PayrollLock = env.storage().instance()
-            .get(&DataKey::PayrollLock(employer.clone(), batch_id))
+        let token: Address = env.storage()
+            .instance()
+            .get(&DataKey::TokenAddress)
             .ok_or(Error::NotInitialized)?;
+        let employers = employer_page(&env, start, limit)?;
         
-        // Verify caller is the employer who locked the funds
-        if lock.employer != employer {
-            return Err(Error::Unauthorized);
+        // Sum obligations, grouping unreleased shares by vault so each vault is queried once
+        // (yield shares left invested after release back their own yield, so are left out)
+        let mut obligations: i128 = 0;
+        let mut shares_by_vault: Map<Address, i128> = Map::new(&env);
+        for employer in employers.iter() {
+            for batch_id in batch_ids(&env, &employer).iter() {
+                let lock: Option<PayrollLock> = load(&env, &DataKey::PayrollLock(employer.clone(), batch_id));
+                if let Some(lock) = lock {
+                    if lock.status == LockStatus::Locked {
+                        obligations = obligations.saturating_add(lock.total_amount);
+                        let shares = shares_by_vault.get(lock.vault.clone()).unwrap_or(0);
+                        shares_by_vault.set(lock.vault, shares.saturating_add(lock.vault_shares));
+                    }
+                    obligations = obligations.saturating_add(lock.yield_earned);
+                    if !lock.employee_yield_distributed {
+                        obligations = obligations.saturating_add(lock.employee_yield);
+                    }
+                }
+            }
         }
         
-        // Verify funds have been released to defindex (FIXED BUG)
-        if !lock.funds_released {
-            return Err(Error::NotYetReleased);
+        // Deposits not yet locked are owed back to their employers too; counted once, with
+        // the balance they sit in
+        let mut holdings: i128 = 0;
+        if start == 0 {
+            let deposits: i128 = env.storage()
+                .instance()
+                .get(&DataKey::TotalDeposits)
+                .unwrap_or(0);
+            obligations = obligations.saturating_add(deposits);
+            holdings = TokenClient::new(&env, &token).balance(&env.current_contract_address());
         }
         
-        // Verify yield hasn't already been claimed
-        if lock.yield_claimed {
-            return Err(Error::AlreadyClaimed);
+        for (vault, shares) in shares_by_vault.iter() {
+            let value = DefindexVaultClient::new(&env, &vault)
+                .get_asset_amounts_per_shares(&shares)
+                .get(0)
+                .unwrap_or(0);
+            holdings = holdings.saturating_add(value);
         }
         
-        // Get stored token address
-        let token: Address = env.storage()
-            .instance()
-            .get(&DataKey::TokenAddress)
-            .ok_or(Error::NotInitialized)?;
+        let end = start.saturating_add(employers.len());
+        Ok(SolvencyPage {
+            obligations,
+            holdings,
+            next_start: if end < employer_count(&env) { Some(end) } else { None },
+        })
+    }
+    
+    /// Get the number of batches ever created by an employer (0 if never locked)
+    /// This counts created batches, not live ones: released batches are included
+    pub fn get_batch_count(env: Env, employer: Address) -> u64 {
+        bump_instance(&env);
         
-        // Calculate employer's share
-        let employer_share = lock.yield_earned;
+        load(&env, &DataKey::NextBatchId(employer))
+            .unwrap_or(0)
+    }
+    
+    /// Get the batch_id the employer's next lock will receive
+    /// Pure read: unlike the other getters it does not extend the instance TTL, so use a
+    /// TTL-extending read (or `bump_ttl`) when keeping the instance alive matters
+    pub fn peek_next_batch_id(env: Env, employer: Address) -> u64 {
+        load(&env, &DataKey::NextBatchId(employer))
+            .unwrap_or(0)
+    }
+    
+    /// Check whether a batch exists and a release would pass its checks now: reached its
+    /// payout date, unreleased, backed by vault shares and no async redemption pending
+    pub fn is_releasable(env: Env, employer: Address, batch_id: u64) -> bool {
+        bump_instance(&env);
         
-        // Transfer yield to employer
-        let token_client = TokenClient::new(&env, &token);
-        token_client.transfer(
-            &env.current_contract_address(),
-            &employer,
-            &employer_share,
-        );
+        let lock: Option<PayrollLock> = load(&env, &DataKey::PayrollLock(employer, batch_id));
+        
+        lock.is_some_and(|lock| check_releasable(&env, &lock).is_ok())
+    }
+    
+    /// Get batch IDs that are unreleased and due within `within_seconds` from now
+    /// Lets keepers discover which batches to pass to `release_to_sdp`
+    pub fn get_due_batches(env: Env, employer: Address, within_seconds: u64) -> Vec<u64> {
+        bump_instance(&env);
+        
+        let deadline = env.ledger().timestamp().saturating_add(within_seconds);
+        
+        let mut due = Vec::new(&env);
+        for batch_id in batch_ids(&env, &employer).iter() {
+            let lock: Option<PayrollLock> = load(&env, &DataKey::PayrollLock(employer.clone(), batch_id));
+            
+            if let Some(lock) = lock {
+                if lock.status == LockStatus::Locked && lock.payout_date <= deadline {
+                    due.push_back(batch_id);
+                }
+            }
+        }
         
-        // Mark yield as claimed
-        lock.yield_claimed = true;
-        env.storage().instance().set(&DataKey::PayrollLock(employer.clone(), batch_id), &lock);
+        due
+    }
+    
+    /// Export the full lock state as an XDR-encoded receipt for off-chain record-keeping
+    pub fn export_receipt(env: Env, employer: Address, batch_id: u64) -> Result<Bytes, Error> {
+        let lock = Self::get_status(env.clone(), employer, batch_id)?;
+        Ok(lock.to_xdr(&env))
+    }
+    
+    /// Verify that a receipt produced by `export_receipt` matches the stored lock state
+    /// Returns false for undecodable receipts or receipts that no longer match
+    pub fn verify_receipt(env: Env, employer: Address, batch_id: u64, receipt: Bytes) -> Result<bool, Error> {
+        let lock = Self::get_status(env.clone(), employer, batch_id)?;
         
-        env.events().publish((symbol_short!("yield"), batch_id), employer);
-        Ok(employer_share)
+        match PayrollLock::from_xdr(&env, &receipt) {
+            Ok(decoded) => Ok(decoded == lock),
+            Err(_) => Ok(false),
+        }
     }
     
-    /// Get current payroll lock status
-    pub fn get_status(env: Env, employer: Address, batch_id: u64) -> Result<PayrollLock, Error> {
-        env.storage()
-            .instance()
-            .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+    /// Get the lifecycle transitions of a batch (most recent 16), oldest first
+    pub fn get_lock_history(env: Env, employer: Address, batch_id: u64) -> Vec<Transition> {
+        bump_instance(&env);
         
-        env.storage().instance()
-            .get(&DataKey::PayrollLock(employer, batch_id))
-            .ok_or(Error::NotInitialized)
+        load(&env, &DataKey::History(employer, batch_id))
+            .unwrap_or(Vec::new(&env))
+    }
+    
+    /// Get per-employee allocations for a batch (empty if none were recorded)
+    pub fn get_allocations(env: Env, employer: Address, batch_id: u64) -> Vec<(Address, i128)> {
+        bump_instance(&env);
+        
+        load(&env, &DataKey::Allocations(employer, batch_id))
+            .unwrap_or(Vec::new(&env))
     }
     
     /// Calculate current yield (can be called anytime to check progress)
     pub fn calculate_current_yield(env: Env, employer: Address, batch_id: u64) -> Result<i128, Error> {
-        env.storage()
-            .instance()
-            .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        bump_instance(&env);
         
-        let lock: PayrollLock = env.storage().instance()
-            .get(&DataKey::PayrollLock(employer, batch_id))
-            .ok_or(Error::NotInitialized)?;
+        let lock: PayrollLock = load(&env, &DataKey::PayrollLock(employer, batch_id))
+            .ok_or(Error::BatchNotFound)?;
+        
+        estimate_yield(&env, &lock, env.ledger().timestamp())
+    }
+    
+    /// Record a batch's unrealized yield from the vault's current share value without
+    /// withdrawing, for periodic NAV snapshots. Returns the snapshot value
+    pub fn snapshot_yield(env: Env, employer: Address, batch_id: u64) -> Result<i128, Error> {
+        require_not_frozen(&env)?;
+        
+        bump_instance(&env);
+        
+        let key = DataKey::PayrollLock(employer, batch_id);
+        let mut lock: PayrollLock = load(&env, &key)
+            .ok_or(Error::BatchNotFound)?;
+        
+        check_not_cancelled(&lock)?;
+        
+        if lock.funds_released {
+            return Err(Error::AlreadyReleased);
+        }
+        
+        let current_value = DefindexVaultClient::new(&env, &lock.vault)
+            .get_asset_amounts_per_shares(&lock.vault_shares)
+            .get(0)
+            .unwrap_or(0);
+        
+        // Can be negative if the vault is currently below the locked principal
+        lock.snapshot_yield = current_value
+            .checked_sub(lock.total_amount)
+            .ok_or(Error::InvalidAmount)?;
+        lock.snapshot_at = env.ledger().timestamp();
+        save_lock(&env, &key, &lock);
+        
+        Ok(lock.snapshot_yield)
+    }
+    
+    /// Projected yield if the batch stays locked until its payout date (APY-based)
+    pub fn project_yield_at_payout(env: Env, employer: Address, batch_id: u64) -> Result<i128, Error> {
+        bump_instance(&env);
+        
+        let lock: PayrollLock = load(&env, &DataKey::PayrollLock(employer, batch_id))
+            .ok_or(Error::BatchNotFound)?;
+        
+        estimate_yield(&env, &lock, lock.payout_date)
+    }
+    
+    /// Annualized yield a released batch actually achieved, in bps: its realized yield (plus
+    /// the current value of yield still invested) over principal and days locked
+    /// Returns 0 for batches locked less than a day
+    pub fn realized_apy_bps(env: Env, employer: Address, batch_id: u64) -> Result<i128, Error> {
+        let lock = Self::get_status(env.clone(), employer, batch_id)?;
+        
+        check_not_cancelled(&lock)?;
+        
+        if !lock.funds_released {
+            return Err(Error::NotYetReleased);
+        }
+        
+        let days_locked = lock.release_date.saturating_sub(lock.lock_date) / 86400;
+        if days_locked == 0 || lock.total_amount <= 0 {
+            return Ok(0);
+        }
+        
+        let total_yield = lock.realized_yield
+            .saturating_add(unclaimed_yield(&env, &lock) - lock.yield_earned);
+        total_yield
+            .checked_mul(365 * BPS_DENOMINATOR)
+            .and_then(|v| v.checked_div(lock.total_amount))
+            .and_then(|v| v.checked_div(days_locked as i128))
+            .ok_or(Error::ArithmeticOverflow)
+    }
+    
+    /// Preview what releasing a batch would move at current share prices, so keepers can
+    /// budget and simulate the right (single or multi-asset) withdrawal path
+    pub fn release_preview(env: Env, employer: Address, batch_id: u64) -> Result<ReleasePreview, Error> {
+        let lock = Self::get_status(env.clone(), employer, batch_id)?;
         
-        // Calculate yield based on time elapsed (4% APY) with overflow protection
-        let days_locked = (env.ledger().timestamp() - lock.lock_date) / 86400;
-        let current_yield = lock.total_amount
-            .checked_mul(4)
-            .and_then(|v| v.checked_mul(days_locked as i128))
-            .and_then(|v| v.checked_div(365 * 100))
-            .ok_or(Error::InsufficientFunds)?;
+        check_not_cancelled(&lock)?;
         
-        Ok(current_yield)
+        if lock.funds_released {
+            return Err(Error::AlreadyReleased);
+        }
+        
+        let value = DefindexVaultClient::new(&env, &lock.vault)
+            .get_asset_amounts_per_shares(&lock.vault_shares)
+            .get(0)
+            .unwrap_or(0);
+        Ok(ReleasePreview {
+            principal: value.min(lock.total_amount),
+            yield_earned: (value - lock.total_amount).max(0),
+            withdraw_shares: release_shares(&env, &lock)?,
+            multi_asset: lock.assets.len() > 1,
+            releasable: check_releasable(&env, &lock).is_ok(),
+        })
+    }
+    
+    /// Configured-token amount a release could withdraw right now: the batch's current value,
+    /// capped by the vault's idle liquidity (0 once released)
+    pub fn max_withdrawable(env: Env, employer: Address, batch_id: u64) -> Result<i128, Error> {
+        bump_instance(&env);
+        
+        let lock: PayrollLock = load(&env, &DataKey::PayrollLock(employer, batch_id))
+            .ok_or(Error::BatchNotFound)?;
+        
+        if lock.status != LockStatus::Locked || lock.vault_shares <= 0 {
+            return Ok(0);
+        }
+        
+        let defindex_client = DefindexVaultClient::new(&env, &lock.vault);
+        let (token, _) = lock.assets.get_unchecked(0);
+        let lock_value = defindex_client
+            .get_asset_amounts_per_shares(&lock.vault_shares)
+            .get(0)
+            .unwrap_or(0);
+        let liquidity = defindex_client
+            .fetch_current_idle_funds()
+            .get(token)
+            .unwrap_or(0);
+        
+        Ok(lock_value.min(liquidity).max(0))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test;
\ No newline at end of file
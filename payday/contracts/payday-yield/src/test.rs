@@ -0,0 +1,3498 @@
+extern crate std;
+use super::*;
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{Address as _, Ledger},
+    token::StellarAssetClient,
+    Address, Env, Vec,
+};
+
+#[contract]
+pub struct MockVault;
+
+#[contractimpl]
+impl MockVault {
+    pub fn init(e: Env, token: Address, bonus: i128) {
+        e.storage().instance().set(&1u32, &token);
+        e.storage().instance().set(&2u32, &bonus);
+    }
+    pub fn deposit(
+        e: Env,
+        amounts_desired: Vec<i128>,
+        amounts_min: Vec<i128>,
+        from: Address,
+        invest: bool,
+    ) -> (Vec<i128>, i128, Vec<()>) {
+        from.require_auth();
+        e.storage().instance().set(&11u32, &invest);
+        let token: Address = e.storage().instance().get(&1u32).unwrap();
+        let mut amt = amounts_desired.get(0).unwrap();
+        let short: i128 = e.storage().instance().get(&5u32).unwrap_or(0);
+        amt -= short;
+        // Like the real vault, revert when it would take less than the caller's minimum
+        assert!(amt >= amounts_min.get(0).unwrap(), "insufficient amount");
+        if amt > 0 {
+            TokenClient::new(&e, &token).transfer(&from, e.current_contract_address(), &amt);
+        }
+        let shares_override: Option<i128> = e.storage().instance().get(&6u32);
+        let mut v = Vec::new(&e);
+        v.push_back(amt);
+        let t2: Option<Address> = e.storage().instance().get(&8u32);
+        if let Some(t2) = t2 {
+            let a2 = amounts_desired.get(1).unwrap();
+            TokenClient::new(&e, &t2).transfer(&from, e.current_contract_address(), &a2);
+            v.push_back(a2);
+            e.storage().instance().set(&9u32, &a2);
+        }
+        (v, shares_override.unwrap_or(amt), Vec::new(&e))
+    }
+    pub fn total_supply(e: Env) -> i128 {
+        e.storage().instance().get(&3u32).unwrap_or(0)
+    }
+    pub fn get_asset_amounts_per_shares(e: Env, vault_shares: i128) -> Vec<i128> {
+        let ts: i128 = e.storage().instance().get(&3u32).unwrap_or(0);
+        let ta: i128 = e.storage().instance().get(&4u32).unwrap_or(0);
+        let mut v = Vec::new(&e);
+        v.push_back(if ts == 0 { 0 } else { vault_shares * ta / ts });
+        v
+    }
+    pub fn balance(e: Env, id: Address) -> i128 {
+        e.storage().instance().get(&(10u32, id)).unwrap_or(0)
+    }
+    pub fn transfer(e: Env, from: Address, to: Address, amount: i128) {
+        let fb: i128 = e
+            .storage()
+            .instance()
+            .get(&(10u32, from.clone()))
+            .unwrap_or(0);
+        let tb: i128 = e
+            .storage()
+            .instance()
+            .get(&(10u32, to.clone()))
+            .unwrap_or(0);
+        e.storage().instance().set(&(10u32, from), &(fb - amount));
+        e.storage().instance().set(&(10u32, to), &(tb + amount));
+    }
+    pub fn mint_shares(e: Env, to: Address, amount: i128) {
+        let b: i128 = e
+            .storage()
+            .instance()
+            .get(&(10u32, to.clone()))
+            .unwrap_or(0);
+        e.storage().instance().set(&(10u32, to), &(b + amount));
+    }
+    pub fn last_invest(e: Env) -> bool {
+        e.storage().instance().get(&11u32).unwrap()
+    }
+    pub fn get_assets(e: Env) -> Vec<defindex_client::AssetStrategySet> {
+        let token: Address = e.storage().instance().get(&1u32).unwrap();
+        let mut v = Vec::new(&e);
+        v.push_back(defindex_client::AssetStrategySet {
+            address: token,
+            strategies: Vec::new(&e),
+        });
+        if let Some(t2) = e.storage().instance().get::<_, Address>(&8u32) {
+            v.push_back(defindex_client::AssetStrategySet {
+                address: t2,
+                strategies: Vec::new(&e),
+            });
+        }
+        v
+    }
+    pub fn set_priced(e: Env) {
+        e.storage().instance().set(&13u32, &true);
+    }
+    pub fn set_delay(e: Env, d: u64) {
+        e.storage().instance().set(&14u32, &d);
+    }
+    pub fn request_withdraw(e: Env, _df_amount: i128, from: Address) -> u64 {
+        from.require_auth();
+        e.ledger().timestamp() + e.storage().instance().get::<_, u64>(&14u32).unwrap_or(0)
+    }
+    pub fn set_idle(e: Env, amt: i128) {
+        e.storage().instance().set(&12u32, &amt);
+    }
+    pub fn fetch_current_idle_funds(e: Env) -> soroban_sdk::Map<Address, i128> {
+        let token: Address = e.storage().instance().get(&1u32).unwrap();
+        let idle: i128 =
+            e.storage().instance().get(&12u32).unwrap_or_else(|| {
+                TokenClient::new(&e, &token).balance(&e.current_contract_address())
+            });
+        soroban_sdk::map![&e, (token, idle)]
+    }
+    pub fn set_second(e: Env, t: Address) {
+        e.storage().instance().set(&8u32, &t);
+    }
+    pub fn set_empty(e: Env) {
+        e.storage().instance().set(&7u32, &true);
+    }
+    pub fn set_bonus(e: Env, b: i128) {
+        e.storage().instance().set(&2u32, &b);
+    }
+    pub fn set_short(e: Env, short: i128) {
+        e.storage().instance().set(&5u32, &short);
+    }
+    pub fn set_shares(e: Env, shares: i128) {
+        e.storage().instance().set(&6u32, &shares);
+    }
+    pub fn set_totals(e: Env, assets: i128, shares: i128) {
+        e.storage().instance().set(&3u32, &shares);
+        e.storage().instance().set(&4u32, &assets);
+    }
+    pub fn withdraw(e: Env, df_amount: i128, _min: Vec<i128>, from: Address) -> Vec<i128> {
+        from.require_auth();
+        let token: Address = e.storage().instance().get(&1u32).unwrap();
+        let bonus: i128 = e.storage().instance().get(&2u32).unwrap();
+        let priced: bool = e.storage().instance().get(&13u32).unwrap_or(false);
+        let out = if priced {
+            let ts: i128 = e.storage().instance().get(&3u32).unwrap();
+            let ta: i128 = e.storage().instance().get(&4u32).unwrap();
+            df_amount * ta / ts
+        } else {
+            df_amount + bonus
+        };
+        let empty: bool = e.storage().instance().get(&7u32).unwrap_or(false);
+        if empty {
+            return Vec::new(&e);
+        }
+        TokenClient::new(&e, &token).transfer(&e.current_contract_address(), &from, &out);
+        let mut v = Vec::new(&e);
+        v.push_back(out);
+        let t2: Option<Address> = e.storage().instance().get(&8u32);
+        if let Some(t2) = t2 {
+            let a2: i128 = e.storage().instance().get(&9u32).unwrap();
+            let o2 = a2 + bonus;
+            TokenClient::new(&e, &t2).transfer(&e.current_contract_address(), &from, &o2);
+            v.push_back(o2);
+        }
+        v
+    }
+}
+
+pub struct Setup {
+    pub env: Env,
+    pub client: PayrollYieldContractClient<'static>,
+    pub token: Address,
+    pub vault: Address,
+    pub admin: Address,
+    pub collector: Address,
+    pub employer: Address,
+}
+
+pub fn setup(bonus: i128) -> Setup {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+    env.ledger().with_mut(|l| l.timestamp = 1_000_000);
+    let admin = Address::generate(&env);
+    let collector = Address::generate(&env);
+    let employer = Address::generate(&env);
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let token = sac.address();
+    let vault = env.register(MockVault, ());
+    MockVaultClient::new(&env, &vault).init(&token, &bonus);
+    StellarAssetClient::new(&env, &token).mint(&employer, &1_000_000);
+    StellarAssetClient::new(&env, &token).mint(&vault, &1_000_000);
+    let id = env.register(PayrollYieldContract, (admin.clone(),));
+    let client = PayrollYieldContractClient::new(&env, &id);
+    Setup {
+        env,
+        client,
+        token,
+        vault,
+        admin,
+        collector,
+        employer,
+    }
+}
+
+pub fn init(s: &Setup, fee: u32, tol: i128) {
+    init_with(
+        s,
+        fee,
+        tol,
+        Settings {
+            rounding_tolerance: Some(tol),
+            ..Default::default()
+        },
+    );
+}
+pub fn init_with(s: &Setup, fee: u32, _tol: i128, st: Settings) {
+    s.client
+        .initialize(&s.vault, &s.admin, &fee, &s.collector, &st);
+    s.client.add_operator(&s.admin, &s.admin);
+}
+
+pub fn lock(s: &Setup, emp: &Address, amt: &i128, date: &u64) -> u64 {
+    s.client
+        .lock_payroll(emp, amt, date, &0, &None, &LockOptions::default())
+}
+
+pub fn solvent(s: &Setup) -> bool {
+    let (mut obligations, mut holdings, mut start) = (0, 0, Some(0));
+    while let Some(at) = start {
+        let page = s.client.check_solvency(&at, &2);
+        obligations += page.obligations;
+        holdings += page.holdings;
+        start = page.next_start;
+    }
+    holdings >= obligations
+}
+
+#[test]
+fn lock_with_allocations() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    let a = Address::generate(&s.env);
+    let b = Address::generate(&s.env);
+    let plain = lock(&s, &s.employer, &10, &1_000_100);
+    assert!(s.client.get_allocations(&s.employer, &plain).is_empty());
+    assert_eq!(
+        s.client.try_lock_with_allocations(
+            &s.employer,
+            &100,
+            &1_000_100,
+            &0,
+            &soroban_sdk::vec![&s.env, (a.clone(), 60i128), (b.clone(), 39i128)],
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        s.client.try_lock_with_allocations(
+            &s.employer,
+            &100,
+            &1_000_100,
+            &0,
+            &soroban_sdk::vec![&s.env, (a.clone(), 110i128), (b.clone(), -10i128)],
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(tc.balance(&s.employer), 1_000_000 - 10);
+    let allocations = soroban_sdk::vec![&s.env, (a.clone(), 60i128), (b.clone(), 40i128)];
+    let id = s
+        .client
+        .lock_with_allocations(&s.employer, &100, &1_000_100, &0, &allocations);
+    assert_eq!(s.client.get_allocations(&s.employer, &id), allocations);
+    assert_eq!(s.client.get_status(&s.employer, &id).total_amount, 100);
+    assert_eq!(tc.balance(&s.employer), 1_000_000 - 110);
+    assert_eq!(tc.balance(&s.vault), 1_000_000 + 110);
+}
+
+#[test]
+fn platform_fee_on_yield() {
+    use soroban_sdk::{testutils::Events, IntoVal, TryFromVal, Val};
+    let s = setup(100);
+    init(&s, 1000, 10);
+    assert_eq!(lock(&s, &s.employer, &10_000, &1_000_100), 0);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let sdp = Address::generate(&s.env);
+    let y = s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(sdp.clone()),
+    );
+    assert_eq!(y, 100);
+    let tc = TokenClient::new(&s.env, &s.token);
+    assert_eq!(tc.balance(&sdp), 10_000);
+    let before = tc.balance(&s.employer);
+    assert_eq!(s.client.claim_yield(&s.employer, &0), 90);
+    let want: Vec<Val> = (symbol_short!("fee"), 0u64).into_val(&s.env);
+    let (_, _, data) = s
+        .env
+        .events()
+        .all()
+        .iter()
+        .find(|(_, t, _)| *t == want)
+        .unwrap();
+    assert_eq!(
+        <(Address, i128)>::try_from_val(&s.env, &data).unwrap(),
+        (s.collector.clone(), 10)
+    );
+    assert_eq!(tc.balance(&s.collector), 10);
+    assert_eq!(tc.balance(&s.employer), before + 90);
+}
+
+#[test]
+fn due_batches() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &s.employer, &10, &1_000_500);
+    lock(&s, &s.employer, &10, &1_000_050);
+    assert_eq!(
+        s.client.get_due_batches(&s.employer, &100),
+        soroban_sdk::vec![&s.env, 0u64, 2]
+    );
+    assert!(s.client.get_due_batches(&s.employer, &49).is_empty());
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_100);
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &0, &ReleaseDestination::Employer);
+    assert_eq!(
+        s.client.get_due_batches(&s.employer, &400),
+        soroban_sdk::vec![&s.env, 1u64, 2]
+    );
+}
+
+#[test]
+fn rounding_tolerance() {
+    use soroban_sdk::{testutils::Events, IntoVal, Val};
+    let s = setup(-5);
+    init(&s, 0, 5);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let sdp = Address::generate(&s.env);
+    assert_eq!(
+        s.client.release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(sdp.clone())
+        ),
+        0
+    );
+    // A shortfall within the tolerance is rounding dust: paid out as is, no loss recorded
+    let loss: Vec<Val> = (symbol_short!("loss"), 0u64).into_val(&s.env);
+    assert!(s.env.events().all().iter().all(|(_, t, _)| t != loss));
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&sdp), 95);
+    let l = s.client.get_status(&s.employer, &0);
+    assert_eq!(
+        (l.status, l.principal_loss, l.yield_earned),
+        (LockStatus::Released, 0, 0)
+    );
+}
+
+#[test]
+fn receipt() {
+    let s = setup(10);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    lock(&s, &s.employer, &200, &1_000_100);
+    let r = s.client.export_receipt(&s.employer, &0);
+    assert_eq!(
+        PayrollLock::from_xdr(&s.env, &r).unwrap(),
+        s.client.get_status(&s.employer, &0)
+    );
+    assert!(s.client.verify_receipt(&s.employer, &0, &r));
+    assert!(!s.client.verify_receipt(&s.employer, &1, &r));
+    // A receipt stops matching once the batch moves on
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &0, &ReleaseDestination::Employer);
+    assert!(!s.client.verify_receipt(&s.employer, &0, &r));
+    assert!(s
+        .client
+        .verify_receipt(&s.employer, &0, &s.client.export_receipt(&s.employer, &0)));
+}
+
+#[test]
+fn zero_yield_claim() {
+    use soroban_sdk::{testutils::Events, IntoVal, Val};
+    let s = setup(0);
+    init(&s, 1000, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_100);
+    let tc = TokenClient::new(&s.env, &s.token);
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &0, &ReleaseDestination::Employer);
+    let before = tc.balance(&s.employer);
+    assert_eq!(s.client.claim_yield(&s.employer, &0), 0);
+    let events = s.env.events().all();
+    // No fee and no transfer, from this contract or the token
+    let fee: Vec<Val> = (symbol_short!("fee"), 0u64).into_val(&s.env);
+    assert!(events.iter().all(|(c, t, _)| c != s.token && t != fee));
+    let claimed: Vec<Val> = (symbol_short!("yield"), 0u64).into_val(&s.env);
+    assert!(events.iter().any(|(_, t, _)| t == claimed));
+    assert!(s.client.get_status(&s.employer, &0).yield_claimed);
+    assert_eq!(tc.balance(&s.employer), before);
+    assert_eq!(tc.balance(&s.collector), 0);
+}
+
+#[test]
+fn future_lock_date() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &365_000, &(1_000_000 + 20 * 86400));
+    // The ledger clock reads behind the lock date (skew or a replayed ledger)
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_000 - 3 * 86400);
+    assert_eq!(s.client.calculate_current_yield(&s.employer, &0), 0);
+    assert_eq!(s.client.project_yield_at_payout(&s.employer, &0), 800);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_000 + 86400);
+    assert_eq!(s.client.calculate_current_yield(&s.employer, &0), 40);
+}
+
+#[test]
+fn tvl_cap() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    lock(&s, &s.employer, &50, &1_000_100);
+    assert!(s.client.set_max_tvl(&s.admin, &60));
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &11,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::TvlCapExceeded))
+    );
+    assert_eq!(tc.balance(&s.employer), 1_000_000 - 50);
+    lock(&s, &s.employer, &10, &1_000_100);
+    assert_eq!(s.client.get_tvl(), 60);
+    // Releasing frees room under the cap
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &0, &ReleaseDestination::Employer);
+    lock(&s, &s.employer, &50, &1_000_300);
+    assert_eq!(tc.balance(&s.employer), 1_000_000 - 60);
+}
+
+#[test]
+fn multiple_sdp() {
+    use soroban_sdk::{testutils::Events, IntoVal, Val};
+    let s = setup(100);
+    init(&s, 0, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    let b = lock(&s, &s.employer, &9_000, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let w1 = Address::generate(&s.env);
+    let w2 = Address::generate(&s.env);
+    let w3 = Address::generate(&s.env);
+    assert_eq!(
+        s.client.try_release_to_multiple_sdp(
+            &s.admin,
+            &s.employer,
+            &b,
+            &soroban_sdk::vec![&s.env, (w1.clone(), 3000i128), (w2.clone(), 3000i128)],
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+    let splits = soroban_sdk::vec![
+        &s.env,
+        (w1.clone(), 3000i128),
+        (w2.clone(), 3000i128),
+        (w3.clone(), 3000i128)
+    ];
+    assert_eq!(
+        s.client
+            .release_to_multiple_sdp(&s.admin, &s.employer, &b, &splits),
+        100
+    );
+    let events = s.env.events().all();
+    for w in [&w1, &w2, &w3] {
+        let want: Vec<Val> = (symbol_short!("sdp_ready"), w.clone()).into_val(&s.env);
+        assert!(events.iter().any(|(_, t, _)| t == want));
+        assert_eq!(tc.balance(w), 3000);
+    }
+    assert_eq!(s.client.claim_yield(&s.employer, &b), 100);
+}
+
+#[test]
+fn claimable() {
+    let s = setup(100);
+    init(&s, 1000, 10);
+    lock(&s, &s.employer, &10_000, &1_000_100);
+    assert_eq!(s.client.get_claimable_yield(&s.employer, &0), 0);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(Address::generate(&s.env).clone()),
+    );
+    assert_eq!(s.client.get_claimable_yield(&s.employer, &0), 90);
+    s.client.claim_yield(&s.employer, &0);
+    assert_eq!(s.client.get_claimable_yield(&s.employer, &0), 0);
+}
+
+#[test]
+fn transfer_ownership() {
+    let s = setup(100);
+    init(&s, 0, 10);
+    let n = Address::generate(&s.env);
+    StellarAssetClient::new(&s.env, &s.token).mint(&n, &100_000);
+    lock(&s, &n, &1_000, &1_000_100);
+    lock(&s, &s.employer, &10_000, &1_000_100);
+    let nb = s.client.transfer_lock_ownership(&s.employer, &0, &n);
+    assert_eq!(nb, 1);
+    assert!(s.client.try_get_status(&s.employer, &0).is_err());
+    assert_eq!(s.client.get_status(&n, &1).employer, n);
+    let from = s.client.get_summary(&s.employer);
+    assert_eq!((from.total_locks, from.amount_locked), (0, 0));
+    let to = s.client.get_summary(&n);
+    assert_eq!((to.total_locks, to.amount_locked), (2, 11_000));
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_100);
+    let sdp = Address::generate(&s.env);
+    s.client
+        .release_to_sdp(&s.admin, &n, &1, &ReleaseDestination::Sdp(sdp.clone()));
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&sdp), 10_000);
+    let from = s.client.get_summary(&s.employer);
+    assert_eq!(
+        (from.total_locks, from.total_released, from.amount_released),
+        (0, 0, 0)
+    );
+    let to = s.client.get_summary(&n);
+    assert_eq!(
+        (to.total_locks, to.total_released, to.amount_released),
+        (2, 1, 10_000)
+    );
+}
+
+#[test]
+fn transfer_ownership_checks() {
+    let s = setup(0);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            max_locked_per_employer: Some(100),
+            ..Default::default()
+        },
+    );
+    let n = Address::generate(&s.env);
+    StellarAssetClient::new(&s.env, &s.token).mint(&n, &1_000);
+    lock(&s, &n, &60, &1_000_100);
+    let k = soroban_sdk::BytesN::from_array(&s.env, &[7u8; 32]);
+    let opts = LockOptions {
+        idempotency_key: Some(k.clone()),
+        ..Default::default()
+    };
+    s.client
+        .lock_payroll(&s.employer, &50, &1_000_100, &0, &None, &opts);
+    assert_eq!(
+        s.client.try_transfer_lock_ownership(&s.employer, &0, &n),
+        Err(Ok(Error::CapExceeded))
+    );
+    s.client.block_address(&s.admin, &n);
+    assert_eq!(
+        s.client.try_transfer_lock_ownership(&s.employer, &0, &n),
+        Err(Ok(Error::Unauthorized))
+    );
+    let m = Address::generate(&s.env);
+    assert_eq!(s.client.transfer_lock_ownership(&s.employer, &0, &m), 0);
+    // Retrying the original lock makes a new batch instead of pointing at the moved one
+    assert_eq!(
+        s.client
+            .lock_payroll(&s.employer, &50, &1_000_100, &0, &None, &opts),
+        1
+    );
+    assert_eq!(s.client.get_status(&s.employer, &1).total_amount, 50);
+}
+
+#[test]
+fn bump_uninit() {
+    let s = setup(0);
+    s.client.bump_ttl();
+}
+
+#[test]
+fn realized() {
+    let s = setup(100);
+    init(&s, 0, 10);
+    lock(&s, &s.employer, &365_000, &1_000_100);
+    assert_eq!(s.client.get_status(&s.employer, &0).realized_yield, 0);
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_000 + 86400 * 10);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(Address::generate(&s.env).clone()),
+    );
+    let l = s.client.get_status(&s.employer, &0);
+    assert_eq!(l.realized_yield, 100);
+    assert_eq!(l.estimated_yield, 400);
+}
+
+#[test]
+fn non_operator_rejected() {
+    let s = setup(100);
+    init(&s, 0, 10);
+    lock(&s, &s.employer, &1_000, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let x = Address::generate(&s.env);
+    assert_eq!(
+        s.client
+            .try_release_to_sdp(&x, &s.employer, &0, &ReleaseDestination::Sdp(x.clone())),
+        Err(Ok(Error::Unauthorized))
+    );
+    s.client.remove_operator(&s.admin, &s.admin);
+    assert!(s
+        .client
+        .try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(x.clone())
+        )
+        .is_err());
+}
+
+#[test]
+fn est_shares() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    assert_eq!(s.client.estimate_shares(&100), 100);
+    MockVaultClient::new(&s.env, &s.vault).set_totals(&2000, &1000);
+    assert_eq!(s.client.estimate_shares(&100), 50);
+}
+
+#[test]
+fn min_shares_guard() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &1000,
+            &1_000_100,
+            &1001,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::InsufficientShares))
+    );
+    assert_eq!(
+        TokenClient::new(&s.env, &s.token).balance(&s.employer),
+        1_000_000
+    );
+}
+
+#[test]
+fn batch_count() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    assert_eq!(s.client.get_batch_count(&s.employer), 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &s.employer, &10, &1_000_100);
+    assert_eq!(s.client.get_batch_count(&s.employer), 2);
+}
+
+#[test]
+fn agent_lock() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let agent = Address::generate(&s.env);
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &Some(agent.clone()),
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::Unauthorized))
+    );
+    s.client.authorize_agent(&s.employer, &agent);
+    TokenClient::new(&s.env, &s.token).approve(&s.employer, &s.client.address, &100, &1000);
+    assert_eq!(
+        s.client.lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &Some(agent.clone()),
+            &LockOptions::default()
+        ),
+        0
+    );
+    assert_eq!(s.client.get_status(&s.employer, &0).total_amount, 10);
+}
+
+#[test]
+fn tvl() {
+    let s = setup(5);
+    init(&s, 0, 0);
+    assert_eq!(s.client.get_tvl(), 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    lock(&s, &s.employer, &50, &1_000_100);
+    assert_eq!(s.client.get_tvl(), 150);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.admin.clone()),
+    );
+    // Only principal counts: yield coming back with the release doesn't
+    assert_eq!(s.client.get_tvl(), 50);
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&s.admin), 100);
+    s.client.claim_yield(&s.employer, &0);
+    assert_eq!(s.client.get_tvl(), 50);
+}
+
+#[test]
+fn sweep() {
+    let s = setup(100);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            claim_window_secs: Some(1000),
+            ..Default::default()
+        },
+    );
+    s.client.add_operator(&s.admin, &s.admin);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.admin.clone()),
+    );
+    assert_eq!(
+        s.client
+            .try_sweep_unclaimed_yield(&s.admin, &s.employer, &0),
+        Err(Ok(Error::ClaimWindowActive))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_001_200);
+    assert_eq!(
+        s.client.sweep_unclaimed_yield(&s.admin, &s.employer, &0),
+        Some(100)
+    );
+    assert_eq!(
+        TokenClient::new(&s.env, &s.token).balance(&s.collector),
+        100
+    );
+}
+
+#[test]
+fn migrate() {
+    use soroban_sdk::{IntoVal, Map, Symbol, Val};
+    let s = setup(0);
+    init(&s, 0, 0);
+    // Written field by field as the single-lock contract stored it, not via LegacyPayrollLock
+    let e = &s.env;
+    let mut legacy: Map<Symbol, Val> = Map::new(e);
+    legacy.set(Symbol::new(e, "employer"), s.employer.into_val(e));
+    legacy.set(Symbol::new(e, "total_amount"), 500i128.into_val(e));
+    legacy.set(Symbol::new(e, "vault_shares"), 500i128.into_val(e));
+    legacy.set(Symbol::new(e, "lock_date"), 1u64.into_val(e));
+    legacy.set(Symbol::new(e, "payout_date"), 2u64.into_val(e));
+    legacy.set(Symbol::new(e, "yield_earned"), 0i128.into_val(e));
+    legacy.set(Symbol::new(e, "funds_released"), false.into_val(e));
+    legacy.set(Symbol::new(e, "yield_claimed"), false.into_val(e));
+    let key = soroban_sdk::vec![e, Symbol::new(e, "PayrollLock")];
+    e.as_contract(&s.client.address, || {
+        e.storage().instance().set(&key, &legacy)
+    });
+    assert_eq!(
+        s.client
+            .try_reinitialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default()),
+        Err(Ok(Error::AlreadyInUse))
+    );
+    assert_eq!(s.client.migrate_legacy_lock(), Some(0));
+    assert_eq!(s.client.migrate_legacy_lock(), None);
+    let lock = s.client.get_status(&s.employer, &0);
+    assert_eq!(
+        (lock.total_amount, lock.vault_shares, lock.payout_date),
+        (500, 500, 2)
+    );
+    assert_eq!(lock.status, LockStatus::Locked);
+    assert_eq!(s.client.get_batch_count(&s.employer), 1);
+    assert_eq!(s.client.get_tvl(), 500);
+    assert_eq!(s.client.get_summary(&s.employer).amount_locked, 500);
+}
+#[test]
+fn init_event() {
+    use soroban_sdk::{testutils::Events, IntoVal, TryFromVal, Val};
+    let s = setup(0);
+    s.client
+        .initialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default());
+    let want: Vec<Val> = (symbol_short!("init"),).into_val(&s.env);
+    let hits: std::vec::Vec<_> = s
+        .env
+        .events()
+        .all()
+        .iter()
+        .filter(|(c, t, _)| *c == s.client.address && *t == want)
+        .collect();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(
+        <(Address, Address, Address)>::try_from_val(&s.env, &hits[0].2).unwrap(),
+        (s.vault.clone(), s.token.clone(), s.admin.clone())
+    );
+    // A rejected re-init emits nothing
+    assert!(s
+        .client
+        .try_initialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default())
+        .is_err());
+    assert!(s.env.events().all().is_empty());
+}
+
+#[test]
+fn registry() {
+    let s = setup(0);
+    let a2 = Address::generate(&s.env);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            co_admins: Some(soroban_sdk::vec![&s.env, a2.clone()]),
+            ..Default::default()
+        },
+    );
+    let e2 = Address::generate(&s.env);
+    StellarAssetClient::new(&s.env, &s.token).mint(&e2, &1000);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &e2, &10, &1_000_100);
+    assert_eq!(s.client.get_employers(&s.admin, &0, &50).len(), 2);
+    // Co-admins can list employers too, anyone else can't
+    assert_eq!(s.client.get_employers(&a2, &0, &50).len(), 2);
+    assert_eq!(
+        s.client.try_get_employers(&e2, &0, &50),
+        Err(Ok(Error::Unauthorized))
+    );
+}
+
+#[test]
+fn residual_refund() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    s.env.mock_all_auths_allowing_non_root_auth();
+    MockVaultClient::new(&s.env, &s.vault).set_short(&30);
+    // `min_shares` is what bounds a partial deposit
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &100,
+            &1_000_100,
+            &71,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::InsufficientShares))
+    );
+    lock(&s, &s.employer, &100, &1_000_100);
+    assert_eq!(s.client.get_status(&s.employer, &0).total_amount, 70);
+    assert_eq!(
+        TokenClient::new(&s.env, &s.token).balance(&s.employer),
+        1_000_000 - 70
+    );
+    assert_eq!(s.client.get_tvl(), 70);
+}
+
+#[test]
+fn releasable() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    assert!(!s.client.is_releasable(&s.employer, &0));
+    assert!(!s.client.is_releasable(&s.employer, &5));
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_100);
+    assert!(s.client.is_releasable(&s.employer, &0));
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.admin.clone()),
+    );
+    assert!(!s.client.is_releasable(&s.employer, &0));
+}
+
+#[test]
+fn min_lock() {
+    let s = setup(0);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            min_lock_secs: Some(100),
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_099,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::LockTooShort))
+    );
+    lock(&s, &s.employer, &10, &1_000_100);
+}
+
+#[test]
+fn all_due() {
+    let s = setup(5);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &s.employer, &10, &1_000_500);
+    lock(&s, &s.employer, &10, &1_000_150);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let w = Address::generate(&s.env);
+    let stranger = Address::generate(&s.env);
+    assert_eq!(
+        s.client
+            .try_release_all_due(&stranger, &s.employer, &w, &0, &50),
+        Err(Ok(Error::Unauthorized))
+    );
+    // The employer can sweep their own due batches without an operator
+    let v = s
+        .client
+        .release_all_due(&s.employer, &s.employer, &w, &0, &50);
+    assert_eq!(v.len(), 2);
+    assert_eq!((v.get(0).unwrap().0, v.get(1).unwrap().0), (0, 2));
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&w), 20);
+    assert!(!s.client.get_status(&s.employer, &1).funds_released);
+}
+
+#[test]
+fn all_due_skips_pending_redemption() {
+    let s = setup(5);
+    init(&s, 0, 0);
+    MockVaultClient::new(&s.env, &s.vault).set_delay(&500);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.client.request_withdrawal(&s.employer, &0);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    assert!(!s.client.is_releasable(&s.employer, &0));
+    assert!(!s.client.release_preview(&s.employer, &0).releasable);
+    assert!(s.client.is_releasable(&s.employer, &1));
+    let w = Address::generate(&s.env);
+    assert_eq!(
+        s.client
+            .release_all_due(&s.admin, &s.employer, &w, &0, &50)
+            .len(),
+        1
+    );
+    assert!(!s.client.get_status(&s.employer, &0).funds_released);
+    assert!(s.client.get_status(&s.employer, &1).funds_released);
+}
+
+#[test]
+fn apy_param() {
+    let s = setup(0);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            estimate_apy_bps: Some(550),
+            ..Default::default()
+        },
+    );
+    lock(&s, &s.employer, &365_000, &1_000_000_000);
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_000 + 86400 * 10);
+    assert_eq!(
+        s.client.calculate_current_yield(&s.employer, &0),
+        365_000 * 550 * 10 / (365 * 10000)
+    );
+}
+
+#[test]
+fn withdraw_guards() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    // The vault hands back nothing: no principal may go out that never came back
+    MockVaultClient::new(&s.env, &s.vault).set_empty();
+    let sdp = Address::generate(&s.env);
+    assert_eq!(
+        s.client.try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(sdp.clone())
+        ),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    assert_eq!(tc.balance(&sdp), 0);
+    assert_eq!(tc.balance(&s.vault), 1_000_000 + 100);
+    assert_eq!(
+        s.client.get_status(&s.employer, &0).status,
+        LockStatus::Locked
+    );
+    assert_eq!(s.client.get_tvl(), 100);
+}
+
+#[test]
+fn release_records_loss() {
+    use soroban_sdk::{testutils::Events, IntoVal, TryFromVal, Val};
+    let s = setup(-6);
+    init(&s, 0, 5);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let sdp = Address::generate(&s.env);
+    // A shortfall beyond the tolerance reverts until admins accept the loss
+    assert_eq!(
+        s.client.try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(sdp.clone())
+        ),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&sdp), 0);
+    assert_eq!(
+        s.client.get_status(&s.employer, &0).status,
+        LockStatus::Locked
+    );
+    assert!(s
+        .client
+        .accept_principal_loss(&s.admin, &s.employer, &0, &5));
+    assert_eq!(
+        s.client.try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(sdp.clone())
+        ),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    assert!(s
+        .client
+        .accept_principal_loss(&s.admin, &s.employer, &0, &6));
+    assert_eq!(
+        s.client.release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(sdp.clone())
+        ),
+        0
+    );
+    let want: Vec<Val> = (symbol_short!("loss"), 0u64).into_val(&s.env);
+    let (_, _, data) = s
+        .env
+        .events()
+        .all()
+        .iter()
+        .find(|(_, t, _)| *t == want)
+        .unwrap();
+    assert_eq!(
+        <(Address, i128)>::try_from_val(&s.env, &data).unwrap(),
+        (s.employer.clone(), 6)
+    );
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&sdp), 94);
+    let l = s.client.get_status(&s.employer, &0);
+    assert_eq!(
+        (l.status, l.principal_loss, l.yield_earned),
+        (LockStatus::Released, 6, 0)
+    );
+    assert_eq!(s.client.get_tvl(), 0);
+}
+
+#[test]
+fn idem() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let k = soroban_sdk::BytesN::from_array(&s.env, &[7u8; 32]);
+    assert_eq!(
+        s.client.lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions {
+                idempotency_key: Some(k.clone()),
+                ..Default::default()
+            }
+        ),
+        0
+    );
+    assert_eq!(
+        s.client.lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions {
+                idempotency_key: Some(k.clone()),
+                ..Default::default()
+            }
+        ),
+        0
+    );
+    assert_eq!(s.client.get_tvl(), 10);
+}
+
+#[test]
+fn status_batch() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    let r = s
+        .client
+        .get_status_batch(&s.employer, &soroban_sdk::vec![&s.env, 0u64, 5u64]);
+    assert!(r.get(0).unwrap().is_some());
+    assert!(r.get(1).unwrap().is_none());
+    let mut many = soroban_sdk::Vec::new(&s.env);
+    for i in 0..51u64 {
+        many.push_back(i);
+    }
+    assert_eq!(
+        s.client.try_get_status_batch(&s.employer, &many),
+        Err(Ok(Error::TooManyBatches))
+    );
+}
+
+#[test]
+fn multi_asset() {
+    let s = setup(5);
+    init(&s, 0, 0);
+    let sac2 = s.env.register_stellar_asset_contract_v2(s.admin.clone());
+    let eurc = sac2.address();
+    StellarAssetClient::new(&s.env, &eurc).mint(&s.employer, &1_000);
+    StellarAssetClient::new(&s.env, &eurc).mint(&s.vault, &1_000);
+    MockVaultClient::new(&s.env, &s.vault).set_second(&eurc);
+    let b = s.client.lock_payroll_multi(
+        &s.employer,
+        &soroban_sdk::vec![&s.env, 100i128, 50i128],
+        &soroban_sdk::vec![&s.env, s.token.clone(), eurc.clone()],
+        &1_000_100,
+        &100,
+    );
+    let st = s.client.get_status(&s.employer, &b);
+    assert_eq!(st.total_amount, 100);
+    assert_eq!(st.assets.get(1).unwrap(), (eurc.clone(), 50));
+    assert_eq!(s.client.get_tvl(), 100);
+    assert_eq!(
+        s.client.try_lock_payroll_multi(
+            &s.employer,
+            &soroban_sdk::vec![&s.env, 50i128, 100i128],
+            &soroban_sdk::vec![&s.env, eurc.clone(), s.token.clone()],
+            &1_000_100,
+            &0
+        ),
+        Err(Ok(Error::InvalidAssets))
+    );
+    // Every token must be one of the vault's assets, listed once
+    let other = s
+        .env
+        .register_stellar_asset_contract_v2(s.admin.clone())
+        .address();
+    for tokens in [
+        soroban_sdk::vec![&s.env, s.token.clone(), other],
+        soroban_sdk::vec![&s.env, s.token.clone(), s.token.clone()],
+        soroban_sdk::vec![&s.env, s.token.clone()],
+    ] {
+        let mut amounts = soroban_sdk::vec![&s.env, 100i128];
+        if tokens.len() > 1 {
+            amounts.push_back(50);
+        }
+        assert_eq!(
+            s.client
+                .try_lock_payroll_multi(&s.employer, &amounts, &tokens, &1_000_100, &0),
+            Err(Ok(Error::InvalidAssets))
+        );
+    }
+    assert_eq!(
+        s.client.try_lock_payroll_multi(
+            &s.employer,
+            &soroban_sdk::vec![&s.env, 100i128, 50i128],
+            &soroban_sdk::vec![&s.env, s.token.clone(), eurc.clone()],
+            &1_000_100,
+            &101
+        ),
+        Err(Ok(Error::InsufficientShares))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let w = Address::generate(&s.env);
+    assert_eq!(
+        s.client.release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &b,
+            &ReleaseDestination::Sdp(w.clone())
+        ),
+        5
+    );
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&w), 100);
+    assert_eq!(TokenClient::new(&s.env, &eurc).balance(&w), 50);
+    assert_eq!(TokenClient::new(&s.env, &eurc).balance(&s.employer), 955);
+}
+
+#[test]
+fn version() {
+    let s = setup(0);
+    assert_eq!(s.client.version(), 22);
+}
+
+#[test]
+fn residual_shares() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &s.employer, &20, &1_000_500);
+    let v = MockVaultClient::new(&s.env, &s.vault);
+    v.mint_shares(&s.client.address, &23); // 20 live + 3 dust
+    assert_eq!(
+        s.client
+            .try_sweep_residual_shares(&s.admin, &s.employer, &0, &s.collector),
+        Err(Ok(Error::NotYetReleased))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    assert_eq!(
+        s.client
+            .sweep_residual_shares(&s.admin, &s.employer, &0, &s.collector),
+        Some(3)
+    );
+    assert_eq!(v.balance(&s.collector), 3);
+    assert_eq!(
+        s.client
+            .sweep_residual_shares(&s.admin, &s.employer, &0, &s.collector),
+        Some(0)
+    );
+}
+
+#[test]
+fn employer_cap() {
+    let s = setup(0);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            max_locked_per_employer: Some(100),
+            ..Default::default()
+        },
+    );
+    lock(&s, &s.employer, &60, &1_000_100);
+    lock(&s, &s.employer, &39, &1_000_100);
+    lock(&s, &s.employer, &1, &1_000_100);
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &1,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::CapExceeded))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    lock(&s, &s.employer, &60, &1_000_300);
+}
+
+#[test]
+fn breakdown() {
+    for (fee, exp_fee) in [(0u32, 0i128), (1000, 10)] {
+        let s = setup(100);
+        init(&s, fee, 0);
+        lock(&s, &s.employer, &10, &1_000_100);
+        assert_eq!(
+            s.client.get_yield_breakdown(&s.employer, &0),
+            YieldBreakdown::default()
+        );
+        s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+        s.client.release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(s.collector.clone()),
+        );
+        let b = s.client.get_yield_breakdown(&s.employer, &0);
+        assert_eq!(
+            b,
+            YieldBreakdown {
+                employer_share: 100 - exp_fee,
+                employee_share: 0,
+                platform_fee: exp_fee
+            }
+        );
+    }
+}
+
+#[test]
+fn release_and_claim() {
+    let s = setup(7);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let w = Address::generate(&s.env);
+    let before = TokenClient::new(&s.env, &s.token).balance(&s.employer);
+    assert_eq!(s.client.release_and_claim(&s.employer, &0, &w), 7);
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&w), 10);
+    assert_eq!(
+        TokenClient::new(&s.env, &s.token).balance(&s.employer),
+        before + 7
+    );
+}
+
+#[test]
+fn batch_not_found() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    assert_eq!(
+        s.client.try_claim_yield(&s.employer, &3),
+        Err(Ok(Error::BatchNotFound))
+    );
+    assert_eq!(
+        s.client.try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &3,
+            &ReleaseDestination::Sdp(s.collector.clone())
+        ),
+        Err(Ok(Error::BatchNotFound))
+    );
+    assert_eq!(
+        s.client.try_get_status(&s.employer, &3),
+        Err(Ok(Error::BatchNotFound))
+    );
+    assert_eq!(
+        s.client.try_calculate_current_yield(&s.employer, &3),
+        Err(Ok(Error::BatchNotFound))
+    );
+    let s2 = setup(0);
+    assert_eq!(
+        s2.client.try_lock_payroll(
+            &s2.employer,
+            &1,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::NotInitialized))
+    );
+}
+
+#[test]
+fn invest_flag() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let v = MockVaultClient::new(&s.env, &s.vault);
+    lock(&s, &s.employer, &10, &1_000_100);
+    assert!(v.last_invest());
+    s.client.lock_payroll(
+        &s.employer,
+        &10,
+        &1_000_100,
+        &0,
+        &None,
+        &LockOptions {
+            invest: Some(false),
+            ..Default::default()
+        },
+    );
+    assert!(!v.last_invest());
+}
+
+#[test]
+fn share_price() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    assert_eq!(s.client.get_share_price(), 0);
+    MockVaultClient::new(&s.env, &s.vault).set_totals(&150, &100);
+    assert_eq!(s.client.get_share_price(), 15_000_000);
+}
+
+#[test]
+fn partial_claim() {
+    let s = setup(100);
+    init(&s, 1000, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    assert_eq!(
+        s.client.try_claim_yield_partial(&s.employer, &0, &101),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    assert_eq!(s.client.claim_yield_partial(&s.employer, &0, &40), 36);
+    let st = s.client.get_status(&s.employer, &0);
+    assert_eq!(
+        (st.yield_earned, st.yield_claimed, st.realized_yield),
+        (60, false, 100)
+    );
+    assert_eq!(s.client.claim_yield_partial(&s.employer, &0, &60), 54);
+    assert!(s.client.get_status(&s.employer, &0).yield_claimed);
+    assert_eq!(
+        s.client.try_claim_yield(&s.employer, &0),
+        Err(Ok(Error::AlreadyClaimed))
+    );
+}
+
+#[test]
+fn keeper_reward() {
+    let s = setup(100);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            keeper_reward_bps: Some(500),
+            ..Default::default()
+        },
+    );
+    s.client.add_operator(&s.admin, &s.admin);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let k = Address::generate(&s.env);
+    assert_eq!(
+        s.client.try_release_by_keeper(&k, &s.employer, &0),
+        Err(Ok(Error::Unauthorized))
+    );
+    let w = Address::generate(&s.env);
+    s.client.set_sdp_wallet(&s.employer, &w);
+    assert_eq!(s.client.release_by_keeper(&k, &s.employer, &0), 5);
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&k), 5);
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&w), 10);
+    assert_eq!(s.client.claim_yield(&s.employer, &0), 95);
+}
+
+#[test]
+fn horizon() {
+    let s = setup(0);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            max_lock_horizon_secs: Some(1000),
+            ..Default::default()
+        },
+    );
+    lock(&s, &s.employer, &10, &1_001_000);
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &10,
+            &1_001_001,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::PayoutTooFar))
+    );
+}
+
+#[test]
+fn relock() {
+    let s = setup(100);
+    init(&s, 1000, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    let bal = TokenClient::new(&s.env, &s.token).balance(&s.employer);
+    let b = s.client.relock_yield(&s.employer, &0, &1_000_500);
+    assert_eq!(s.client.get_status(&s.employer, &b).total_amount, 90);
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&s.employer), bal);
+    assert!(s.client.get_status(&s.employer, &0).yield_claimed);
+    assert_eq!(s.client.get_tvl(), 90);
+}
+
+#[test]
+fn decimals() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    assert_eq!(s.client.get_token_decimals(), 7);
+    assert_eq!(s.client.get_token_decimals(), 7);
+}
+
+#[test]
+fn token_from_vault() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    assert_eq!(vault_token(&s.env, &s.vault), Ok(s.token.clone()));
+    lock(&s, &s.employer, &10, &1_000_100);
+}
+
+#[test]
+fn unbacked_lock() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    // A batch whose deposit never got confirmed with vault shares
+    let key = DataKey::PayrollLock(s.employer.clone(), 0);
+    s.env.as_contract(&s.client.address, || {
+        let mut l: PayrollLock = load(&s.env, &key).unwrap();
+        l.vault_shares = 0;
+        save_lock(&s.env, &key, &l);
+    });
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let sdp = Address::generate(&s.env);
+    assert!(!s.client.is_releasable(&s.employer, &0));
+    assert_eq!(
+        s.client.try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(sdp.clone())
+        ),
+        Err(Ok(Error::InsufficientShares))
+    );
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&sdp), 0);
+    assert_eq!(
+        s.client.get_status(&s.employer, &0).status,
+        LockStatus::Locked
+    );
+}
+
+#[test]
+fn set_vault() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    let v2 = s.env.register(MockVault, ());
+    MockVaultClient::new(&s.env, &v2).init(&s.token, &3);
+    StellarAssetClient::new(&s.env, &s.token).mint(&v2, &1_000);
+    s.client.set_defindex_vault(&s.admin, &v2);
+    assert_eq!(s.client.get_defindex_vault(), v2);
+    assert_eq!(s.client.get_status(&s.employer, &0).vault, s.vault);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    assert_eq!(
+        s.client.release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(s.collector.clone())
+        ),
+        0
+    );
+    assert_eq!(
+        s.client.release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &1,
+            &ReleaseDestination::Sdp(s.collector.clone())
+        ),
+        3
+    );
+}
+
+#[test]
+fn swept_event() {
+    use soroban_sdk::{symbol_short, testutils::Events, IntoVal, TryFromVal, Val};
+    let s = setup(100);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            claim_window_secs: Some(0),
+            ..Default::default()
+        },
+    );
+    s.client.add_operator(&s.admin, &s.admin);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.admin.clone()),
+    );
+    s.client.sweep_unclaimed_yield(&s.admin, &s.employer, &0);
+    let (_, topics, data) = s.env.events().all().last().unwrap();
+    let expected: Vec<Val> = (symbol_short!("swept"), 0u64).into_val(&s.env);
+    assert_eq!(topics, expected);
+    assert_eq!(
+        <(i128, Address)>::try_from_val(&s.env, &data).unwrap(),
+        (100, s.collector.clone())
+    );
+}
+
+#[test]
+fn projection() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &365_000, &(1_000_000 + 10 * 86400));
+    assert_eq!(s.client.project_yield_at_payout(&s.employer, &0), 400);
+    assert_eq!(s.client.calculate_current_yield(&s.employer, &0), 0);
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_000 + 5 * 86400);
+    assert_eq!(s.client.calculate_current_yield(&s.employer, &0), 200);
+    assert_eq!(s.client.project_yield_at_payout(&s.employer, &0), 400);
+}
+
+#[test]
+fn sdp_ready_event() {
+    use soroban_sdk::{symbol_short, testutils::Events, IntoVal, TryFromVal, Val};
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let w = Address::generate(&s.env);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(w.clone()),
+    );
+    let want: Vec<Val> = (symbol_short!("sdp_ready"), w.clone()).into_val(&s.env);
+    let hits: std::vec::Vec<_> = s
+        .env
+        .events()
+        .all()
+        .iter()
+        .filter(|(_, t, _)| *t == want)
+        .collect();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(
+        <(u64, i128, Address)>::try_from_val(&s.env, &hits[0].2).unwrap(),
+        (0, 10, s.employer.clone())
+    );
+}
+
+#[test]
+fn no_double_release() {
+    use soroban_sdk::{testutils::Events, IntoVal, Val};
+    let s = setup(3);
+    init(&s, 0, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    let released: Vec<Val> = (symbol_short!("released"), 0u64, 3i128).into_val(&s.env);
+    assert!(s.env.events().all().iter().any(|(_, t, _)| t == released));
+    let vault_before = tc.balance(&s.vault);
+    assert_eq!(
+        s.client.try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(s.collector.clone())
+        ),
+        Err(Ok(Error::AlreadyReleased))
+    );
+    assert!(s.env.events().all().is_empty());
+    assert_eq!(tc.balance(&s.collector), 10);
+    assert_eq!(tc.balance(&s.vault), vault_before);
+    assert_eq!(tc.balance(&s.client.address), 3);
+}
+
+// Token that calls back into the payroll contract whenever the contract pays it out
+#[contract]
+pub struct ReentrantToken;
+#[contractimpl]
+impl ReentrantToken {
+    pub fn arm(e: Env, target: Address, operator: Address, employer: Address) {
+        e.storage()
+            .instance()
+            .set(&symbol_short!("target"), &(target, operator, employer));
+    }
+    pub fn reentered(e: Env) -> Option<bool> {
+        e.storage().instance().get(&symbol_short!("reentry"))
+    }
+    pub fn mint(e: Env, to: Address, amt: i128) {
+        let b: i128 = e.storage().instance().get(&to).unwrap_or(0);
+        e.storage().instance().set(&to, &(b + amt));
+    }
+    pub fn balance(e: Env, id: Address) -> i128 {
+        e.storage().instance().get(&id).unwrap_or(0)
+    }
+    pub fn decimals(_e: Env) -> u32 {
+        7
+    }
+    pub fn name(e: Env) -> soroban_sdk::String {
+        soroban_sdk::String::from_str(&e, "reentrant")
+    }
+    pub fn transfer(e: Env, from: Address, to: Address, amt: i128) {
+        from.require_auth();
+        let fb: i128 = e.storage().instance().get(&from).unwrap_or(0);
+        let tb: i128 = e.storage().instance().get(&to).unwrap_or(0);
+        e.storage().instance().set(&from, &(fb - amt));
+        e.storage().instance().set(&to, &(tb + amt));
+        let armed: Option<(Address, Address, Address)> =
+            e.storage().instance().get(&symbol_short!("target"));
+        if let Some((target, operator, employer)) = armed {
+            if from == target {
+                let r = PayrollYieldContractClient::new(&e, &target).try_release_to_sdp(
+                    &operator,
+                    &employer,
+                    &0,
+                    &ReleaseDestination::Sdp(to.clone()),
+                );
+                e.storage()
+                    .instance()
+                    .set(&symbol_short!("reentry"), &matches!(r, Ok(Ok(_))));
+            }
+        }
+    }
+}
+
+#[test]
+fn reentrant_release() {
+    let s = setup(0);
+    let t = s.env.register(ReentrantToken, ());
+    let tc = ReentrantTokenClient::new(&s.env, &t);
+    tc.mint(&s.employer, &1000);
+    let v = s.env.register(MockVault, ());
+    MockVaultClient::new(&s.env, &v).init(&t, &0);
+    s.client
+        .initialize(&v, &s.admin, &0, &s.collector, &Settings::default());
+    s.client.add_operator(&s.admin, &s.admin);
+    lock(&s, &s.employer, &100, &1_000_100);
+    tc.arm(&s.client.address, &s.admin, &s.employer);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let sdp = Address::generate(&s.env);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(sdp.clone()),
+    );
+    // The token called back in mid-payout and could not release the batch again
+    assert_eq!(tc.reentered(), Some(false));
+    assert_eq!(tc.balance(&sdp), 100);
+    assert_eq!(tc.balance(&s.client.address), 0);
+    assert_eq!(tc.balance(&v), 0);
+    assert_eq!(
+        s.client.get_status(&s.employer, &0).status,
+        LockStatus::Released
+    );
+}
+
+#[test]
+fn min_claim() {
+    let s = setup(100);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            min_claimable_yield: Some(50),
+            ..Default::default()
+        },
+    );
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    assert_eq!(
+        s.client.try_claim_yield_partial(&s.employer, &0, &49),
+        Err(Ok(Error::BelowMinimum))
+    );
+    assert_eq!(s.client.claim_yield_partial(&s.employer, &0, &51), 51);
+    // Claiming the last 49 in full is still below the minimum
+    assert_eq!(
+        s.client.try_claim_yield(&s.employer, &0),
+        Err(Ok(Error::BelowMinimum))
+    );
+    assert_eq!(s.client.get_status(&s.employer, &0).yield_earned, 49);
+}
+
+#[test]
+fn min_claim_dust_batch() {
+    let s = setup(10);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            min_claimable_yield: Some(50),
+            employee_yield_bps: Some(5000),
+            claim_window_secs: Some(1_000),
+            ..Default::default()
+        },
+    );
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &0, &ReleaseDestination::Employer);
+    assert_eq!(
+        s.client.try_claim_yield(&s.employer, &0),
+        Err(Ok(Error::BelowMinimum))
+    );
+    // A dust batch is closed out by the sweep once the claim window passes
+    s.env.ledger().with_mut(|l| l.timestamp = 1_001_200);
+    let before = TokenClient::new(&s.env, &s.token).balance(&s.collector);
+    assert_eq!(
+        s.client.sweep_unclaimed_yield(&s.admin, &s.employer, &0),
+        Some(10)
+    );
+    assert_eq!(
+        TokenClient::new(&s.env, &s.token).balance(&s.collector),
+        before + 10
+    );
+    assert_eq!(
+        s.client.get_status(&s.employer, &0).status,
+        LockStatus::Claimed
+    );
+}
+
+#[contract]
+pub struct FeeToken;
+#[contractimpl]
+impl FeeToken {
+    pub fn mint(e: Env, to: Address, amt: i128) {
+        let b: i128 = e.storage().instance().get(&to).unwrap_or(0);
+        e.storage().instance().set(&to, &(b + amt));
+    }
+    pub fn balance(e: Env, id: Address) -> i128 {
+        e.storage().instance().get(&id).unwrap_or(0)
+    }
+    pub fn decimals(_e: Env) -> u32 {
+        7
+    }
+    pub fn name(e: Env) -> soroban_sdk::String {
+        soroban_sdk::String::from_str(&e, "fee")
+    }
+    pub fn transfer(e: Env, from: Address, to: Address, amt: i128) {
+        from.require_auth();
+        let fb: i128 = e.storage().instance().get(&from).unwrap_or(0);
+        let tb: i128 = e.storage().instance().get(&to).unwrap_or(0);
+        e.storage().instance().set(&from, &(fb - amt));
+        e.storage().instance().set(&to, &(tb + amt - amt / 10));
+    }
+}
+
+#[test]
+fn fee_on_transfer() {
+    let s = setup(0);
+    let ft = s.env.register(FeeToken, ());
+    let ftc = FeeTokenClient::new(&s.env, &ft);
+    ftc.mint(&s.employer, &1000);
+    let v = s.env.register(MockVault, ());
+    MockVaultClient::new(&s.env, &v).init(&ft, &0);
+    s.client
+        .initialize(&v, &s.admin, &0, &s.collector, &Settings::default());
+    s.client.lock_payroll(
+        &s.employer,
+        &100,
+        &1_000_100,
+        &0,
+        &None,
+        &LockOptions::default(),
+    );
+    let st = s.client.get_status(&s.employer, &0);
+    assert_eq!(st.total_amount, 90);
+    assert_eq!(s.client.get_tvl(), 90);
+    assert_eq!(ftc.balance(&s.client.address), 0);
+}
+
+#[test]
+fn peek() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    assert_eq!(s.client.peek_next_batch_id(&s.employer), 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &s.employer, &10, &1_000_100);
+    assert_eq!(s.client.peek_next_batch_id(&s.employer), 2);
+}
+
+#[test]
+fn cancel_takes_fee_on_yield() {
+    let s = setup(50);
+    init(&s, 1000, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    let before = tc.balance(&s.employer);
+    lock(&s, &s.employer, &100, &1_100_000);
+    assert_eq!(s.client.cancel_lock(&s.employer, &0), 145);
+    assert_eq!(tc.balance(&s.collector), 5);
+    assert_eq!(tc.balance(&s.employer), before + 45);
+}
+
+#[test]
+fn cancel_window() {
+    let s = setup(0);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            cancel_window_secs: Some(3600),
+            ..Default::default()
+        },
+    );
+    s.client.add_operator(&s.admin, &s.admin);
+    let before = TokenClient::new(&s.env, &s.token).balance(&s.employer);
+    lock(&s, &s.employer, &10, &1_100_000);
+    lock(&s, &s.employer, &20, &1_100_000);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_003_600);
+    assert_eq!(s.client.cancel_lock(&s.employer, &0), 10);
+    assert_eq!(
+        s.client.get_status(&s.employer, &0).status,
+        LockStatus::Cancelled
+    );
+    assert!(!s.client.get_status(&s.employer, &0).funds_released);
+    assert_eq!(
+        s.client.try_cancel_lock(&s.employer, &0),
+        Err(Ok(Error::LockCancelled))
+    );
+    assert_eq!(
+        s.client.try_claim_yield(&s.employer, &0),
+        Err(Ok(Error::LockCancelled))
+    );
+    assert_eq!(
+        s.client.try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(s.admin.clone())
+        ),
+        Err(Ok(Error::LockCancelled))
+    );
+    assert_eq!(s.client.get_claimable_yield(&s.employer, &0), 0);
+    assert_eq!(s.client.get_tvl(), 20);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_003_601);
+    assert_eq!(
+        s.client.try_cancel_lock(&s.employer, &1),
+        Err(Ok(Error::CancelWindowClosed))
+    );
+    assert_eq!(
+        TokenClient::new(&s.env, &s.token).balance(&s.employer),
+        before - 20
+    );
+}
+
+#[test]
+fn snapshot() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_100_000);
+    MockVaultClient::new(&s.env, &s.vault).set_totals(&110, &100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_500);
+    assert_eq!(s.client.snapshot_yield(&s.employer, &0), 10);
+    let st = s.client.get_status(&s.employer, &0);
+    assert_eq!(
+        (st.snapshot_yield, st.snapshot_at, st.funds_released),
+        (10, 1_000_500, false)
+    );
+}
+
+#[test]
+fn blocklist() {
+    let s = setup(5);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.client.block_address(&s.admin, &s.employer);
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::Unauthorized))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    assert_eq!(s.client.claim_yield(&s.employer, &0), 5);
+    s.client.unblock_address(&s.admin, &s.employer);
+    lock(&s, &s.employer, &10, &1_000_300);
+}
+
+#[test]
+fn history() {
+    use soroban_sdk::symbol_short;
+    let s = setup(5);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_300);
+    s.client.claim_yield(&s.employer, &0);
+    let h = s.client.get_lock_history(&s.employer, &0);
+    assert_eq!(
+        h,
+        soroban_sdk::vec![
+            &s.env,
+            Transition {
+                kind: symbol_short!("locked"),
+                timestamp: 1_000_000
+            },
+            Transition {
+                kind: symbol_short!("released"),
+                timestamp: 1_000_200
+            },
+            Transition {
+                kind: symbol_short!("claimed"),
+                timestamp: 1_000_300
+            }
+        ]
+    );
+}
+
+#[test]
+fn native_xlm() {
+    let s = setup(0);
+    // register a "native-like" SAC issued by nobody special
+    let native = s
+        .env
+        .register_stellar_asset_contract_v2(Address::generate(&s.env))
+        .address();
+    StellarAssetClient::new(&s.env, &native).mint(&s.employer, &1000);
+    let v = s.env.register(MockVault, ());
+    MockVaultClient::new(&s.env, &v).init(&native, &0);
+    s.client
+        .initialize(&v, &s.admin, &0, &s.collector, &Settings::default());
+    s.client.lock_payroll(
+        &s.employer,
+        &100,
+        &1_000_100,
+        &0,
+        &None,
+        &LockOptions::default(),
+    );
+    let bad = s.env.register(MockVault, ());
+    let v2 = s.env.register(MockVault, ());
+    MockVaultClient::new(&s.env, &v2).init(&bad, &0);
+    let id2 = s.env.register(PayrollYieldContract, (s.admin.clone(),));
+    let c2 = PayrollYieldContractClient::new(&s.env, &id2);
+    assert_eq!(
+        c2.try_initialize(&v2, &s.admin, &0, &s.collector, &Settings::default()),
+        Err(Ok(Error::InvalidToken))
+    );
+}
+
+#[test]
+fn yield_delay() {
+    let s = setup(0);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            yield_start_delay_secs: Some(10 * 86400),
+            estimate_apy_bps: Some(3650),
+            ..Default::default()
+        },
+    );
+    lock(&s, &s.employer, &10000, &2_000_000_000);
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_000 + 5 * 86400);
+    assert_eq!(s.client.calculate_current_yield(&s.employer, &0), 0);
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_000 + 12 * 86400);
+    assert_eq!(s.client.calculate_current_yield(&s.employer, &0), 20);
+}
+
+#[test]
+fn is_admin() {
+    let s = setup(0);
+    assert!(!s.client.is_admin(&s.admin));
+    init(&s, 0, 0);
+    assert!(s.client.is_admin(&s.admin));
+    assert!(!s.client.is_admin(&s.employer));
+}
+
+#[test]
+fn label() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let l = soroban_sdk::String::from_str(&s.env, "March 2025 Engineering");
+    s.client.lock_payroll(
+        &s.employer,
+        &10,
+        &1_000_100,
+        &0,
+        &None,
+        &LockOptions {
+            label: Some(l.clone()),
+            ..Default::default()
+        },
+    );
+    assert_eq!(s.client.get_status(&s.employer, &0).label, Some(l));
+    let long = soroban_sdk::String::from_str(&s.env, "0123456789012345678901234567890123");
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions {
+                label: Some(long),
+                ..Default::default()
+            }
+        ),
+        Err(Ok(Error::InvalidLabel))
+    );
+    assert!(s.client.version() >= 6);
+}
+
+#[test]
+fn max_withdrawable() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    let vc = MockVaultClient::new(&s.env, &s.vault);
+    vc.set_totals(&100, &100);
+    assert_eq!(s.client.max_withdrawable(&s.employer, &0), 100);
+    vc.set_idle(&40);
+    assert_eq!(s.client.max_withdrawable(&s.employer, &0), 40);
+}
+
+#[test]
+fn freeze() {
+    let s = setup(10);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.client.freeze(&s.admin);
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::ContractFrozen))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let w = Address::generate(&s.env);
+    assert_eq!(
+        s.client.try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(w.clone())
+        ),
+        Err(Ok(Error::ContractFrozen))
+    );
+    assert_eq!(
+        s.client.try_claim_yield(&s.employer, &0),
+        Err(Ok(Error::ContractFrozen))
+    );
+    assert_eq!(
+        s.client.try_cancel_lock(&s.employer, &0),
+        Err(Ok(Error::ContractFrozen))
+    );
+    assert_eq!(
+        s.client.try_set_sdp_wallet(&s.employer, &w),
+        Err(Ok(Error::ContractFrozen))
+    );
+    s.client.set_max_tvl(&s.admin, &0);
+    s.client.unfreeze(&s.admin);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(w.clone()),
+    );
+    s.client.claim_yield(&s.employer, &0);
+}
+
+#[test]
+fn salted_ids() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let a = BytesN::from_array(&s.env, &[1; 32]);
+    let b = BytesN::from_array(&s.env, &[2; 32]);
+    let ia = s
+        .client
+        .lock_payroll_with_id(&s.employer, &10, &1_000_100, &a);
+    let ib = s
+        .client
+        .lock_payroll_with_id(&s.employer, &10, &1_000_100, &b);
+    assert_ne!(ia, ib);
+    assert!(ia >= 1 << 63);
+    assert_eq!(
+        s.client
+            .try_lock_payroll_with_id(&s.employer, &10, &1_000_100, &a),
+        Err(Ok(Error::BatchIdTaken))
+    );
+    assert_eq!(s.client.get_status(&s.employer, &ia).total_amount, 10);
+    assert_eq!(s.client.get_batch_count(&s.employer), 0);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    assert_eq!(s.client.get_due_batches(&s.employer, &0).len(), 2);
+    let w = Address::generate(&s.env);
+    assert_eq!(
+        s.client
+            .release_all_due(&s.admin, &s.employer, &w, &0, &50)
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn yield_scaled() {
+    let s = setup(12_345);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(Address::generate(&s.env).clone()),
+    );
+    assert_eq!(s.client.get_yield_scaled(&s.employer, &0, &0), 12_345);
+    assert_eq!(s.client.get_yield_scaled(&s.employer, &0, &3), 12);
+    assert_eq!(s.client.get_yield_remainder(&s.employer, &0, &3), 345);
+    assert_eq!(s.client.get_yield_scaled(&s.employer, &0, &7), 0);
+    assert_eq!(
+        s.client.try_get_yield_scaled(&s.employer, &0, &8),
+        Err(Ok(Error::InvalidScale))
+    );
+}
+
+#[test]
+fn solvency() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    let vc = MockVaultClient::new(&s.env, &s.vault);
+    vc.set_totals(&100, &100);
+    assert!(solvent(&s));
+    vc.set_totals(&90, &100);
+    assert!(!solvent(&s));
+}
+
+#[test]
+fn admin_ops_need_threshold() {
+    let s = setup(5);
+    let a2 = Address::generate(&s.env);
+    let op = Address::generate(&s.env);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            co_admins: Some(soroban_sdk::vec![&s.env, a2.clone()]),
+            admin_threshold: Some(2),
+            claim_window_secs: Some(0),
+            ..Default::default()
+        },
+    );
+    assert!(s.client.is_admin(&a2));
+    assert!(!s.client.add_operator(&s.admin, &op));
+    assert!(s.client.get_operators().is_empty());
+    assert!(s.client.add_operator(&a2, &op));
+    assert_eq!(
+        s.client.get_operators(),
+        soroban_sdk::vec![&s.env, op.clone()]
+    );
+    assert!(!s.client.set_max_tvl(&s.admin, &5));
+    assert!(!s.client.block_address(&a2, &s.employer));
+    lock(&s, &s.employer, &10, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client
+        .release_to_sdp(&op, &s.employer, &0, &ReleaseDestination::Employer);
+    assert_eq!(
+        s.client.sweep_unclaimed_yield(&s.admin, &s.employer, &0),
+        None
+    );
+    assert_eq!(
+        s.client.sweep_unclaimed_yield(&a2, &s.employer, &0),
+        Some(5)
+    );
+    lock(&s, &s.employer, &10, &1_000_300);
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_300 + 90 * 24 * 60 * 60);
+    let to = Address::generate(&s.env);
+    assert_eq!(s.client.admin_cancel_lock(&a2, &s.employer, &1, &to), None);
+    assert_eq!(
+        s.client.admin_cancel_lock(&s.admin, &s.employer, &1, &to),
+        Some(15)
+    );
+}
+
+#[test]
+fn multi_admin() {
+    let s = setup(0);
+    let a2 = Address::generate(&s.env);
+    let a3 = Address::generate(&s.env);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            co_admins: Some(soroban_sdk::vec![&s.env, a2.clone(), a3.clone()]),
+            admin_threshold: Some(2),
+            ..Default::default()
+        },
+    );
+    assert!(!s.client.freeze(&s.admin));
+    assert!(!s.client.freeze(&s.admin));
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Ok(Ok(0))
+    );
+    assert!(s.client.freeze(&a3));
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::ContractFrozen))
+    );
+    assert_eq!(
+        s.client.try_unfreeze(&s.employer),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert!(!s.client.unfreeze(&a2));
+    assert!(s.client.unfreeze(&s.admin));
+    assert_eq!(s.client.get_admins().1, 2);
+    let id2 = s.env.register(PayrollYieldContract, (s.admin.clone(),));
+    let c2 = PayrollYieldContractClient::new(&s.env, &id2);
+    assert_eq!(
+        c2.try_initialize(
+            &s.vault,
+            &s.admin,
+            &0,
+            &s.collector,
+            &Settings {
+                admin_threshold: Some(2),
+                ..Default::default()
+            }
+        ),
+        Err(Ok(Error::InvalidThreshold))
+    );
+}
+
+#[test]
+fn total_shares() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let e2 = Address::generate(&s.env);
+    StellarAssetClient::new(&s.env, &s.token).mint(&e2, &1000);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &s.employer, &20, &1_000_100);
+    lock(&s, &e2, &30, &1_000_100);
+    assert_eq!(s.client.get_total_shares(), 60);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &1,
+        &ReleaseDestination::Sdp(Address::generate(&s.env).clone()),
+    );
+    assert_eq!(s.client.get_total_shares(), 40);
+}
+
+#[test]
+fn release_to_employer() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    let before = tc.balance(&s.employer);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &0, &ReleaseDestination::Employer);
+    assert_eq!(tc.balance(&s.employer), before);
+}
+
+#[test]
+fn yield_compounds() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let vc = MockVaultClient::new(&s.env, &s.vault);
+    lock(&s, &s.employer, &100, &1_000_100);
+    vc.set_priced();
+    vc.set_totals(&200, &100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let sdp = Address::generate(&s.env);
+    assert_eq!(
+        s.client.release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(sdp.clone())
+        ),
+        100
+    );
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&sdp), 100);
+    let st = s.client.get_status(&s.employer, &0);
+    assert_eq!((st.yield_shares, st.yield_earned), (50, 0));
+    assert_eq!(s.client.get_total_shares(), 50);
+    assert_eq!(s.client.get_claimable_yield(&s.employer, &0), 100);
+    vc.set_totals(&240, &100);
+    assert_eq!(s.client.get_claimable_yield(&s.employer, &0), 120);
+    assert_eq!(s.client.claim_yield(&s.employer, &0), 120);
+    let st = s.client.get_status(&s.employer, &0);
+    assert_eq!(
+        (
+            st.yield_shares,
+            st.yield_earned,
+            st.realized_yield,
+            st.yield_claimed
+        ),
+        (0, 0, 120, true)
+    );
+}
+
+#[test]
+fn poke() {
+    let s = setup(5);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            keeper_reward_bps: Some(2000),
+            ..Default::default()
+        },
+    );
+    let k = Address::generate(&s.env);
+    let sdp = Address::generate(&s.env);
+    s.client
+        .lock_payroll_with_sdp(&s.employer, &100, &1_000_100, &sdp);
+    lock(&s, &s.employer, &100, &1_000_100);
+    assert_eq!(
+        s.client.try_poke(&k, &s.employer, &0),
+        Err(Ok(Error::PayoutDateNotReached))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    assert_eq!(
+        s.client.try_poke(&k, &s.employer, &1),
+        Err(Ok(Error::Unauthorized))
+    );
+    // The batch wallet wins over the employer's registered wallet
+    let w = Address::generate(&s.env);
+    s.client.set_sdp_wallet(&s.employer, &w);
+    assert_eq!(s.client.poke(&k, &s.employer, &0), 1);
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&sdp), 100);
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&k), 1);
+    assert_eq!(s.client.poke(&k, &s.employer, &1), 1);
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&w), 100);
+    assert_eq!(s.client.claim_yield(&s.employer, &0), 4);
+}
+
+#[test]
+fn estimate_overflow() {
+    let s = setup(0);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            estimate_apy_bps: Some(u32::MAX),
+            ..Default::default()
+        },
+    );
+    let big: i128 = 10i128.pow(22);
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.employer, &big);
+    lock(&s, &s.employer, &big, &u64::MAX);
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_000 + 1000 * 86400);
+    let r = (u32::MAX as i128) * 1000;
+    assert_eq!(
+        s.client.calculate_current_yield(&s.employer, &0),
+        big / 3_650_000 * r + (big % 3_650_000) * r / 3_650_000
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = u64::MAX - 1);
+    assert_eq!(
+        s.client.try_calculate_current_yield(&s.employer, &0),
+        Err(Ok(Error::ArithmeticOverflow))
+    );
+}
+
+#[test]
+fn estimate_reordered() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let big: i128 = 10i128.pow(22);
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.employer, &big);
+    lock(&s, &s.employer, &big, &u64::MAX);
+    s.env.ledger().with_mut(|l| l.timestamp = u64::MAX - 1);
+    let days = ((u64::MAX - 1 - 1_000_000) / 86400) as i128;
+    assert_eq!(
+        s.client.calculate_current_yield(&s.employer, &0),
+        big / 3_650_000 * 400 * days + (big % 3_650_000) * 400 * days / 3_650_000
+    );
+}
+
+#[test]
+fn realized_apy() {
+    let s = setup(50);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &1000, &(1_000_000 + 73 * 86400));
+    assert_eq!(
+        s.client.try_realized_apy_bps(&s.employer, &0),
+        Err(Ok(Error::NotYetReleased))
+    );
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_000 + 73 * 86400);
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &0, &ReleaseDestination::Employer);
+    // 5% over 73 days = 25% APY
+    assert_eq!(s.client.realized_apy_bps(&s.employer, &0), 2500);
+    s.client.claim_yield(&s.employer, &0);
+    assert_eq!(s.client.realized_apy_bps(&s.employer, &0), 2500);
+}
+
+#[test]
+fn async_withdrawal() {
+    let s = setup(5);
+    init(&s, 0, 0);
+    let vc = MockVaultClient::new(&s.env, &s.vault);
+    vc.set_delay(&500);
+    lock(&s, &s.employer, &100, &1_000_100);
+    let sdp = Address::generate(&s.env);
+    assert_eq!(
+        s.client.try_complete_withdrawal(&s.employer, &0, &sdp),
+        Err(Ok(Error::WithdrawalNotReady))
+    );
+    assert_eq!(s.client.request_withdrawal(&s.employer, &0), 1_000_500);
+    assert_eq!(s.client.request_withdrawal(&s.employer, &0), 1_000_500);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    assert_eq!(
+        s.client.try_complete_withdrawal(&s.employer, &0, &sdp),
+        Err(Ok(Error::WithdrawalNotReady))
+    );
+    assert_eq!(
+        s.client.try_release_to_sdp(
+            &s.admin,
+            &s.employer,
+            &0,
+            &ReleaseDestination::Sdp(sdp.clone())
+        ),
+        Err(Ok(Error::WithdrawalNotReady))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_500);
+    assert_eq!(s.client.complete_withdrawal(&s.employer, &0, &sdp), 5);
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&sdp), 100);
+}
+
+#[test]
+fn negotiated_employer_fee() {
+    let s = setup(100);
+    init(&s, 1000, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    assert_eq!(
+        s.client
+            .try_set_employer_fee(&s.admin, &s.employer, &Some(1001)),
+        Err(Ok(Error::InvalidFee))
+    );
+    assert_eq!(
+        s.client
+            .try_set_employer_fee(&s.employer, &s.employer, &Some(0)),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert!(s.client.set_employer_fee(&s.admin, &s.employer, &Some(300)));
+    assert_eq!(s.client.get_employer_fee(&s.employer), Some(300));
+    lock(&s, &s.employer, &100, &1_000_100);
+    // Batches keep the fee they were locked under
+    s.client.set_employer_fee(&s.admin, &s.employer, &None);
+    assert_eq!(s.client.get_employer_fee(&s.employer), None);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client
+        .release_all_due(&s.admin, &s.employer, &Address::generate(&s.env), &0, &50);
+    assert_eq!(s.client.claim_yield(&s.employer, &0), 90);
+    assert_eq!(
+        s.client.get_yield_breakdown(&s.employer, &1).platform_fee,
+        3
+    );
+    assert_eq!(s.client.claim_yield(&s.employer, &1), 97);
+}
+
+#[test]
+fn per_batch_yield_split() {
+    let s = setup(100);
+    init_with(
+        &s,
+        1000,
+        0,
+        Settings {
+            employee_yield_bps: Some(5000),
+            ..Default::default()
+        },
+    );
+    let split = |bps: Option<u32>| LockOptions {
+        employer_yield_bps: bps,
+        ..Default::default()
+    };
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &1000,
+            &1_000_100,
+            &0,
+            &None,
+            &split(Some(10_001))
+        ),
+        Err(Ok(Error::InvalidConfig))
+    );
+    let a = s.client.lock_payroll(
+        &s.employer,
+        &1000,
+        &1_000_100,
+        &0,
+        &None,
+        &split(Some(8000)),
+    );
+    let b = s.client.lock_payroll(
+        &s.employer,
+        &1000,
+        &1_000_100,
+        &0,
+        &None,
+        &split(Some(2000)),
+    );
+    let c = lock(&s, &s.employer, &1000, &1_000_100);
+    assert_eq!(
+        s.client.get_status(&s.employer, &a).employer_yield_bps,
+        Some(8000)
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let w = Address::generate(&s.env);
+    s.client.release_all_due(&s.admin, &s.employer, &w, &0, &50);
+    // 100 of yield each, 10 to the platform, the other 90 split per batch
+    let tc = TokenClient::new(&s.env, &s.token);
+    let before = tc.balance(&s.employer);
+    assert_eq!(s.client.claim_yield(&s.employer, &a), 72);
+    assert_eq!(s.client.claim_yield(&s.employer, &b), 18);
+    assert_eq!(s.client.claim_yield(&s.employer, &c), 45);
+    assert_eq!(tc.balance(&s.employer), before + 72 + 18 + 45);
+    assert_eq!(s.client.get_status(&s.employer, &a).employee_yield, 18);
+    assert_eq!(s.client.get_status(&s.employer, &b).employee_yield, 72);
+    assert_eq!(s.client.get_status(&s.employer, &c).employee_yield, 45);
+    assert_eq!(tc.balance(&s.collector), 30);
+}
+
+#[test]
+fn release_preview() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let vc = MockVaultClient::new(&s.env, &s.vault);
+    lock(&s, &s.employer, &100, &1_000_100);
+    vc.set_priced();
+    vc.set_totals(&125, &100);
+    let p = s.client.release_preview(&s.employer, &0);
+    assert!(!p.releasable);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let p = s.client.release_preview(&s.employer, &0);
+    assert_eq!(
+        p,
+        ReleasePreview {
+            principal: 100,
+            yield_earned: 25,
+            withdraw_shares: 80,
+            multi_asset: false,
+            releasable: true
+        }
+    );
+    let sdp = Address::generate(&s.env);
+    let y = s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(sdp.clone()),
+    );
+    assert_eq!(y, p.yield_earned);
+    assert_eq!(
+        TokenClient::new(&s.env, &s.token).balance(&sdp),
+        p.principal
+    );
+    assert_eq!(
+        s.client.get_status(&s.employer, &0).yield_shares,
+        100 - p.withdraw_shares
+    );
+}
+
+#[test]
+fn allowance_lock() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    let agent = Address::generate(&s.env);
+    tc.approve(&s.employer, &s.client.address, &150, &1000);
+    // Being a release operator is not enough to spend an employer's allowance
+    assert_eq!(
+        s.client
+            .try_lock_payroll_from_allowance(&s.admin, &s.employer, &10, &1_000_100),
+        Err(Ok(Error::Unauthorized))
+    );
+    s.client.authorize_agent(&s.employer, &agent);
+    s.client
+        .lock_payroll_from_allowance(&agent, &s.employer, &100, &1_000_100);
+    assert!(s.env.auths().iter().all(|(a, _)| *a != s.employer));
+    assert_eq!(s.client.get_status(&s.employer, &0).total_amount, 100);
+    assert_eq!(tc.allowance(&s.employer, &s.client.address), 50);
+    assert!(s
+        .client
+        .try_lock_payroll_from_allowance(&agent, &s.employer, &100, &1_000_100)
+        .is_err());
+}
+
+#[test]
+fn status_lifecycle() {
+    let s = setup(5);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    let l = s.client.get_status(&s.employer, &0);
+    assert_eq!(
+        (l.status, l.funds_released, l.yield_claimed),
+        (LockStatus::Locked, false, false)
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    let l = s.client.get_status(&s.employer, &0);
+    assert_eq!(
+        (l.status, l.funds_released, l.yield_claimed),
+        (LockStatus::Released, true, false)
+    );
+    s.client.claim_yield(&s.employer, &0);
+    let l = s.client.get_status(&s.employer, &0);
+    assert_eq!(
+        (l.status, l.funds_released, l.yield_claimed),
+        (LockStatus::Claimed, true, true)
+    );
+}
+
+#[test]
+fn employer_summary() {
+    let s = setup(5);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            cancel_window_secs: Some(3600),
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        s.client.get_summary(&s.employer),
+        EmployerSummary::default()
+    );
+    lock(&s, &s.employer, &100, &1_000_100);
+    lock(&s, &s.employer, &40, &1_000_100);
+    s.client.cancel_lock(&s.employer, &1);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    s.client.claim_yield(&s.employer, &0);
+    let sum = s.client.get_summary(&s.employer);
+    assert_eq!(
+        (
+            sum.total_locks,
+            sum.total_released,
+            sum.total_claimed,
+            sum.total_cancelled
+        ),
+        (2, 1, 1, 1)
+    );
+    assert_eq!(
+        (sum.amount_locked, sum.amount_released, sum.yield_claimed),
+        (140, 100, 5)
+    );
+}
+
+#[test]
+fn guardian_reinitialize() {
+    let s = setup(0);
+    assert_eq!(
+        s.client
+            .try_reinitialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default()),
+        Err(Ok(Error::NotInitialized))
+    );
+    init(&s, 0, 0);
+    s.client.reinitialize(
+        &s.vault,
+        &s.employer,
+        &50,
+        &s.collector,
+        &Settings::default(),
+    );
+    assert_eq!(s.client.get_admins().0.get(0).unwrap(), s.employer);
+    assert_eq!(
+        s.client
+            .try_reinitialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default()),
+        Err(Ok(Error::AlreadyInitialized))
+    );
+
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &10, &1_000_100);
+    assert_eq!(
+        s.client
+            .try_reinitialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default()),
+        Err(Ok(Error::AlreadyInUse))
+    );
+
+    // Unlocked deposits are held in the configured token, so they block it too
+    let s = setup(0);
+    init(&s, 0, 0);
+    s.client.deposit_funds(&s.employer, &10);
+    assert_eq!(
+        s.client
+            .try_reinitialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default()),
+        Err(Ok(Error::AlreadyInUse))
+    );
+}
+
+#[test]
+fn employee_yield_distribution() {
+    let s = setup(100);
+    init_with(
+        &s,
+        1000,
+        0,
+        Settings {
+            employee_yield_bps: Some(5000),
+            ..Default::default()
+        },
+    );
+    lock(&s, &s.employer, &1000, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let sdp = Address::generate(&s.env);
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &0, &ReleaseDestination::Employer);
+    let b = s.client.get_yield_breakdown(&s.employer, &0);
+    assert_eq!(
+        (b.platform_fee, b.employee_share, b.employer_share),
+        (10, 45, 45)
+    );
+    assert_eq!(s.client.get_claimable_yield(&s.employer, &0), 45);
+    assert_eq!(
+        s.client
+            .try_distribute_employee_yield(&s.employer, &0, &sdp),
+        Err(Ok(Error::InvalidStateTransition))
+    );
+    assert_eq!(s.client.claim_yield(&s.employer, &0), 45);
+    assert!(solvent(&s));
+    assert_eq!(
+        s.client.distribute_employee_yield(&s.employer, &0, &sdp),
+        45
+    );
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&sdp), 45);
+    assert!(
+        s.client
+            .get_status(&s.employer, &0)
+            .employee_yield_distributed
+    );
+    assert_eq!(
+        s.client
+            .try_distribute_employee_yield(&s.employer, &0, &sdp),
+        Err(Ok(Error::AlreadyClaimed))
+    );
+}
+
+#[test]
+fn init_rejects_overlapping_addresses() {
+    let s = setup(0);
+    let me = s.client.address.clone();
+    assert_eq!(
+        s.client
+            .try_initialize(&me, &s.admin, &0, &s.collector, &Settings::default()),
+        Err(Ok(Error::InvalidConfig))
+    );
+    MockVaultClient::new(&s.env, &s.vault).init(&s.vault, &0);
+    assert_eq!(
+        s.client
+            .try_initialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default()),
+        Err(Ok(Error::InvalidConfig))
+    );
+    MockVaultClient::new(&s.env, &s.vault).init(&me, &0);
+    assert_eq!(
+        s.client
+            .try_initialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default()),
+        Err(Ok(Error::InvalidConfig))
+    );
+    MockVaultClient::new(&s.env, &s.vault).init(&s.token, &0);
+    s.client
+        .initialize(&s.vault, &s.admin, &0, &s.collector, &Settings::default());
+}
+
+#[test]
+fn shares_to_assets_rate() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    assert_eq!(s.client.shares_to_assets(&100), 0);
+    MockVaultClient::new(&s.env, &s.vault).set_totals(&1_500, &1_000);
+    assert_eq!(s.client.shares_to_assets(&100), 150);
+    assert_eq!(s.client.shares_to_assets(&0), 0);
+    assert_eq!(
+        s.client.try_shares_to_assets(&-1),
+        Err(Ok(Error::InvalidAmount))
+    );
+}
+
+#[test]
+fn zero_share_release() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    MockVaultClient::new(&s.env, &s.vault).set_shares(&0);
+    let before = TokenClient::new(&s.env, &s.token).balance(&s.employer);
+    assert_eq!(
+        s.client.try_lock_payroll(
+            &s.employer,
+            &10,
+            &1_000_100,
+            &0,
+            &None,
+            &LockOptions::default()
+        ),
+        Err(Ok(Error::InsufficientShares))
+    );
+    assert_eq!(
+        TokenClient::new(&s.env, &s.token).balance(&s.employer),
+        before
+    );
+    assert_eq!(s.client.get_tvl(), 0);
+}
+
+#[test]
+fn per_lock_token_transfers() {
+    let s = setup(10);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    // Simulate the instance moving to a vault for another token
+    let sac_b = s.env.register_stellar_asset_contract_v2(s.admin.clone());
+    let token_b = sac_b.address();
+    let v2 = s.env.register(MockVault, ());
+    MockVaultClient::new(&s.env, &v2).init(&token_b, &20);
+    StellarAssetClient::new(&s.env, &token_b).mint(&s.employer, &1_000);
+    StellarAssetClient::new(&s.env, &token_b).mint(&v2, &1_000);
+    s.env.as_contract(&s.client.address, || {
+        s.env
+            .storage()
+            .instance()
+            .set(&DataKey::TokenAddress, &token_b);
+        s.env
+            .storage()
+            .instance()
+            .set(&DataKey::DefindexPoolAddress, &v2);
+    });
+    lock(&s, &s.employer, &200, &1_000_100);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let a = TokenClient::new(&s.env, &s.token);
+    let b = TokenClient::new(&s.env, &token_b);
+    let (a0, b0) = (a.balance(&s.employer), b.balance(&s.employer));
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &0, &ReleaseDestination::Employer);
+    s.client
+        .release_to_sdp(&s.admin, &s.employer, &1, &ReleaseDestination::Employer);
+    assert_eq!(
+        (a.balance(&s.employer) - a0, b.balance(&s.employer) - b0),
+        (100, 200)
+    );
+    assert_eq!(s.client.claim_yield(&s.employer, &0), 10);
+    assert_eq!(s.client.claim_yield(&s.employer, &1), 20);
+    assert_eq!(
+        (a.balance(&s.employer) - a0, b.balance(&s.employer) - b0),
+        (110, 220)
+    );
+}
+
+#[test]
+fn split_config_reported_and_validated() {
+    let s = setup(0);
+    assert_eq!(
+        s.client.try_get_split_config(),
+        Err(Ok(Error::NotInitialized))
+    );
+    assert_eq!(
+        s.client.try_initialize(
+            &s.vault,
+            &s.admin,
+            &1000,
+            &s.collector,
+            &Settings {
+                employee_yield_bps: Some(10_001),
+                ..Default::default()
+            }
+        ),
+        Err(Ok(Error::InvalidConfig))
+    );
+    init_with(
+        &s,
+        1000,
+        0,
+        Settings {
+            employee_yield_bps: Some(5000),
+            ..Default::default()
+        },
+    );
+    let c = s.client.get_split_config();
+    assert_eq!(
+        (c.employer_bps, c.employee_bps, c.platform_fee_bps),
+        (4500, 4500, 1000)
+    );
+    assert_eq!(c.employer_bps + c.employee_bps + c.platform_fee_bps, 10_000);
+}
+
+#[test]
+fn admin_cancel_timelock() {
+    let s = setup(5);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    let to = Address::generate(&s.env);
+    let unlock = 1_000_100 + 90 * 24 * 60 * 60;
+    s.env.ledger().with_mut(|l| l.timestamp = unlock - 1);
+    assert_eq!(
+        s.client
+            .try_admin_cancel_lock(&s.admin, &s.employer, &0, &to),
+        Err(Ok(Error::TimelockActive))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = unlock);
+    assert_eq!(
+        s.client.admin_cancel_lock(&s.admin, &s.employer, &0, &to),
+        Some(105)
+    );
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&to), 105);
+    assert_eq!(
+        s.client.get_status(&s.employer, &0).status,
+        LockStatus::Cancelled
+    );
+    assert_eq!(
+        s.client
+            .try_admin_cancel_lock(&s.admin, &s.employer, &0, &to),
+        Err(Ok(Error::LockCancelled))
+    );
+    assert_eq!(s.client.get_tvl(), 0);
+}
+
+#[test]
+fn admin_cancel_requires_admin() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.env
+        .ledger()
+        .with_mut(|l| l.timestamp = 1_000_100 + 90 * 24 * 60 * 60);
+    assert_eq!(
+        s.client
+            .try_admin_cancel_lock(&s.employer, &s.employer, &0, &s.employer),
+        Err(Ok(Error::Unauthorized))
+    );
+    s.env.set_auths(&[]);
+    assert!(s
+        .client
+        .try_admin_cancel_lock(&s.admin, &s.employer, &0, &s.employer)
+        .is_err());
+}
+
+#[test]
+fn recurring_locks() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let ids = s
+        .client
+        .lock_payroll_recurring(&s.employer, &100, &1000, &3);
+    assert_eq!(ids, soroban_sdk::vec![&s.env, 0u64, 1, 2]);
+    for (i, id) in ids.iter().enumerate() {
+        let l = s.client.get_status(&s.employer, &id);
+        assert_eq!(
+            (l.payout_date, l.total_amount),
+            (1_000_000 + 1000 * (i as u64 + 1), 100)
+        );
+    }
+    assert_eq!(s.client.get_tvl(), 300);
+    assert_eq!(
+        s.client
+            .try_lock_payroll_recurring(&s.employer, &100, &1000, &0),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        s.client
+            .try_lock_payroll_recurring(&s.employer, &100, &1000, &13),
+        Err(Ok(Error::TooManyBatches))
+    );
+    assert_eq!(
+        s.client
+            .try_lock_payroll_recurring(&s.employer, &100, &0, &2),
+        Err(Ok(Error::InvalidPayoutDate))
+    );
+}
+
+#[test]
+fn contract_balance() {
+    let s = setup(0);
+    assert_eq!(s.client.get_contract_balance(), 0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    assert_eq!(s.client.get_contract_balance(), 0);
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.client.address, &7);
+    assert_eq!(s.client.get_contract_balance(), 7);
+}
+
+#[test]
+fn cancelled_tombstone() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    lock(&s, &s.employer, &100, &1_000_100);
+    lock(&s, &s.employer, &100, &1_000_100);
+    s.client.cancel_lock(&s.employer, &0);
+    assert!(s.client.was_cancelled(&s.employer, &0));
+    assert!(!s.client.was_cancelled(&s.employer, &1));
+    assert!(!s.client.was_cancelled(&s.employer, &7));
+    // The tombstone is a persistent marker of its own, independent of the full record
+    s.env.as_contract(&s.client.address, || {
+        let key = DataKey::Cancelled(s.employer.clone(), 0);
+        assert!(s.env.storage().persistent().has(&key));
+        s.env
+            .storage()
+            .persistent()
+            .remove(&DataKey::PayrollLock(s.employer.clone(), 0));
+    });
+    assert!(s.client.was_cancelled(&s.employer, &0));
+}
+
+#[test]
+fn lock_from_existing_balance() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    assert_eq!(
+        s.client
+            .try_lock_existing_balance(&s.employer, &50, &1_000_100),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    // Tokens sent in directly are not credited to anyone
+    tc.transfer(&s.employer, &s.client.address, &5);
+    assert_eq!(
+        s.client
+            .try_lock_existing_balance(&s.employer, &5, &1_000_100),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    assert_eq!(s.client.deposit_funds(&s.employer, &50), 50);
+    assert_eq!(s.client.get_deposit(&s.employer), 50);
+    let before = tc.balance(&s.employer);
+    assert_eq!(
+        s.client
+            .try_lock_existing_balance(&s.employer, &51, &1_000_100),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    let id = s.client.lock_existing_balance(&s.employer, &50, &1_000_100);
+    assert_eq!(s.client.get_status(&s.employer, &id).total_amount, 50);
+    assert_eq!(tc.balance(&s.employer), before);
+    assert_eq!(s.client.get_deposit(&s.employer), 0);
+    assert_eq!(s.client.get_contract_balance(), 5);
+    assert_eq!(s.client.get_tvl(), 50);
+}
+
+#[test]
+fn deposit_only_lockable_by_depositor() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let tc = TokenClient::new(&s.env, &s.token);
+    let thief = Address::generate(&s.env);
+    s.client.deposit_funds(&s.employer, &100);
+    assert!(solvent(&s));
+    assert_eq!(
+        s.client.try_lock_existing_balance(&thief, &100, &1_000_100),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    assert_eq!(
+        s.client.try_withdraw_deposit(&thief, &1),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    let before = tc.balance(&s.employer);
+    assert_eq!(s.client.withdraw_deposit(&s.employer, &40), 60);
+    assert_eq!(tc.balance(&s.employer), before + 40);
+    assert_eq!(
+        s.client.try_withdraw_deposit(&s.employer, &61),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    s.client.lock_existing_balance(&s.employer, &60, &1_000_100);
+    assert_eq!(s.client.get_contract_balance(), 0);
+    MockVaultClient::new(&s.env, &s.vault).set_totals(&60, &60);
+    assert!(solvent(&s));
+}
+
+#[test]
+fn heartbeat_event() {
+    use soroban_sdk::{testutils::Events, IntoVal};
+    let s = setup(0);
+    assert_eq!(s.client.heartbeat(), 1_000_000);
+    let ev = s.env.events().all();
+    let last = ev.last().unwrap();
+    assert_eq!(last.0, s.client.address);
+    assert_eq!(last.1, (symbol_short!("heartbeat"),).into_val(&s.env));
+    let v: u64 = soroban_sdk::FromVal::from_val(&s.env, &last.2);
+    assert_eq!(v, 1_000_000);
+}
+
+#[test]
+fn admin_locks_across_employers() {
+    let s = setup(0);
+    let a2 = Address::generate(&s.env);
+    init_with(
+        &s,
+        0,
+        0,
+        Settings {
+            co_admins: Some(soroban_sdk::vec![&s.env, a2.clone()]),
+            ..Default::default()
+        },
+    );
+    let e2 = Address::generate(&s.env);
+    StellarAssetClient::new(&s.env, &s.token).mint(&e2, &1_000);
+    lock(&s, &s.employer, &10, &1_000_100);
+    lock(&s, &e2, &20, &1_000_100);
+    let employers = soroban_sdk::vec![&s.env, s.employer.clone(), e2.clone(), e2.clone()];
+    let ids = soroban_sdk::vec![&s.env, 0u64, 0, 5];
+    let got = s.client.admin_get_locks(&s.admin, &employers, &ids);
+    assert_eq!(got.get(0).unwrap().unwrap().total_amount, 10);
+    assert_eq!(got.get(1).unwrap().unwrap().total_amount, 20);
+    assert!(got.get(2).unwrap().is_none());
+    // A co-admin sees the same; an employer can't read across employers
+    assert_eq!(s.client.admin_get_locks(&a2, &employers, &ids), got);
+    assert_eq!(
+        s.client.try_admin_get_locks(&e2, &employers, &ids),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(
+        s.client.try_admin_get_locks(
+            &s.admin,
+            &soroban_sdk::vec![&s.env, e2.clone()],
+            &soroban_sdk::vec![&s.env, 0u64, 1]
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+    let mut many_employers = Vec::new(&s.env);
+    let mut many_ids = Vec::new(&s.env);
+    for i in 0..51u64 {
+        many_employers.push_back(e2.clone());
+        many_ids.push_back(i);
+    }
+    assert_eq!(
+        s.client
+            .try_admin_get_locks(&s.admin, &many_employers, &many_ids),
+        Err(Ok(Error::TooManyBatches))
+    );
+}
+
+#[test]
+fn partial_withdraw_normal() {
+    let s = setup(0);
+    init_with(
+        &s,
+        1000,
+        0,
+        Settings {
+            cancel_window_secs: Some(3600),
+            ..Default::default()
+        },
+    );
+    lock(&s, &s.employer, &100, &1_000_100);
+    let vc = MockVaultClient::new(&s.env, &s.vault);
+    vc.set_priced();
+    vc.set_totals(&150, &100);
+    let before = TokenClient::new(&s.env, &s.token).balance(&s.employer);
+    // 20 shares redeem 30 of yield, 10% of which is the platform fee
+    assert_eq!(
+        s.client
+            .emergency_partial_withdraw(&s.employer, &0, &20, &false),
+        27
+    );
+    let l = s.client.get_status(&s.employer, &0);
+    assert_eq!((l.total_amount, l.vault_shares), (100, 80));
+    assert_eq!(
+        TokenClient::new(&s.env, &s.token).balance(&s.employer),
+        before + 27
+    );
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&s.collector), 3);
+    assert_eq!(s.client.get_tvl(), 100);
+    // The 60 shares left would be worth less than the principal
+    assert_eq!(
+        s.client
+            .try_emergency_partial_withdraw(&s.employer, &0, &20, &false),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    assert_eq!(
+        s.client
+            .try_emergency_partial_withdraw(&s.employer, &0, &80, &true),
+        Err(Ok(Error::InsufficientShares))
+    );
+    assert_eq!(
+        s.client
+            .try_emergency_partial_withdraw(&s.employer, &0, &0, &false),
+        Err(Ok(Error::InvalidAmount))
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_003_601);
+    vc.set_totals(&300, &100);
+    assert_eq!(
+        s.client
+            .try_emergency_partial_withdraw(&s.employer, &0, &1, &false),
+        Err(Ok(Error::CancelWindowClosed))
+    );
+    // Forcing gives up principal but doesn't reopen the window
+    assert_eq!(
+        s.client
+            .try_emergency_partial_withdraw(&s.employer, &0, &1, &true),
+        Err(Ok(Error::CancelWindowClosed))
+    );
+}
+
+#[test]
+fn partial_withdraw_forced() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let emp = Address::generate(&s.env);
+    s.client.lock_with_allocations(
+        &s.employer,
+        &100,
+        &1_000_100,
+        &0,
+        &soroban_sdk::vec![&s.env, (emp, 100i128)],
+    );
+    assert_eq!(
+        s.client
+            .try_emergency_partial_withdraw(&s.employer, &0, &10, &false),
+        Err(Ok(Error::InsufficientFunds))
+    );
+    assert_eq!(
+        s.client
+            .emergency_partial_withdraw(&s.employer, &0, &10, &true),
+        10
+    );
+    assert_eq!(s.client.get_status(&s.employer, &0).total_amount, 90);
+    assert_eq!(s.client.get_summary(&s.employer).amount_locked, 90);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    s.client.release_to_sdp(
+        &s.admin,
+        &s.employer,
+        &0,
+        &ReleaseDestination::Sdp(s.collector.clone()),
+    );
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&s.collector), 90);
+    let summary = s.client.get_summary(&s.employer);
+    assert_eq!((summary.amount_locked, summary.amount_released), (90, 90));
+}
+
+#[test]
+fn paginated_views() {
+    let s = setup(0);
+    init(&s, 0, 0);
+    let e2 = Address::generate(&s.env);
+    let e3 = Address::generate(&s.env);
+    for e in [&e2, &e3] {
+        StellarAssetClient::new(&s.env, &s.token).mint(e, &1_000);
+        lock(&s, e, &10, &1_000_100);
+    }
+    for _ in 0..3 {
+        lock(&s, &s.employer, &10, &1_000_100);
+    }
+    let salt = soroban_sdk::BytesN::from_array(&s.env, &[3u8; 32]);
+    let salted = s
+        .client
+        .lock_payroll_with_id(&s.employer, &10, &1_000_100, &salt);
+    // Records live in persistent storage, not in the instance
+    s.env.as_contract(&s.client.address, || {
+        let key = DataKey::PayrollLock(s.employer.clone(), 0);
+        assert!(s.env.storage().persistent().has(&key));
+        assert!(!s.env.storage().instance().has(&key));
+    });
+    s.client.bump_lock_ttl(&s.employer, &0);
+    assert_eq!(
+        s.client.try_bump_lock_ttl(&s.employer, &9),
+        Err(Ok(Error::BatchNotFound))
+    );
+    assert_eq!(s.client.get_total_shares(), 60);
+    MockVaultClient::new(&s.env, &s.vault).set_totals(&60, &60);
+
+    assert_eq!(
+        s.client.get_employers(&s.admin, &0, &2),
+        soroban_sdk::vec![&s.env, e2.clone(), e3.clone()]
+    );
+    assert_eq!(
+        s.client.get_employers(&s.admin, &2, &2),
+        soroban_sdk::vec![&s.env, s.employer.clone()]
+    );
+    assert!(s.client.get_employers(&s.admin, &5, &2).is_empty());
+    assert_eq!(
+        s.client.try_get_employers(&s.admin, &0, &51),
+        Err(Ok(Error::TooManyBatches))
+    );
+
+    let first = s.client.check_solvency(&0, &2);
+    assert_eq!((first.obligations, first.next_start), (20, Some(2)));
+    let last = s.client.check_solvency(&2, &2);
+    assert_eq!(
+        (last.obligations, last.holdings, last.next_start),
+        (40, 40, None)
+    );
+    assert!(solvent(&s));
+
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_200);
+    let w = Address::generate(&s.env);
+    assert_eq!(
+        s.client
+            .try_release_all_due(&s.admin, &s.employer, &w, &0, &51),
+        Err(Ok(Error::TooManyBatches))
+    );
+    let page = s.client.release_all_due(&s.admin, &s.employer, &w, &0, &2);
+    assert_eq!(page, soroban_sdk::vec![&s.env, (0u64, 0i128), (1, 0)]);
+    let page = s.client.release_all_due(&s.admin, &s.employer, &w, &2, &2);
+    assert_eq!(page, soroban_sdk::vec![&s.env, (2u64, 0i128), (salted, 0)]);
+    assert!(s
+        .client
+        .release_all_due(&s.admin, &s.employer, &w, &4, &2)
+        .is_empty());
+    assert_eq!(TokenClient::new(&s.env, &s.token).balance(&w), 40);
+    assert_eq!(s.client.get_total_shares(), 20);
+    assert!(solvent(&s));
+}